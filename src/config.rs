@@ -33,4 +33,61 @@ pub const EVENT_COUNT: usize = 16;
 #[cfg(feature = "events_64")]
 pub const EVENT_COUNT: usize = 64;
 
-pub const MAX_LOGS: usize = 128;
\ No newline at end of file
+pub const MAX_LOGS: usize = 128;
+
+/// BASEPRI value of the kernel's critical sections with the `basepri` feature. Interrupts with a
+/// priority value below it are never masked by the kernel, and must not call kernel routines.
+pub const KERNEL_BASEPRI: u8 = 0x40;
+
+/// Maximum number of hooks registered with `register_shutdown_hook`.
+pub const MAX_SHUTDOWN_HOOKS: usize = 4;
+
+/// Number of words in the response of the diagnostics buffer.
+pub const DIAG_RESPONSE_WORDS: usize = 128;
+
+/// Maximum cycles a context switch may take with the `latency_monitor` feature, checked in debug
+/// builds. Zero disables the check.
+pub const CONTEXT_SWITCH_BUDGET_CYCLES: u32 = 0;
+
+/// Number of scheduling decisions recorded, and scripted, with the `deterministic` feature.
+pub const DECISION_TRACE_LEN: usize = 64;
+
+/// Number of words at the top of a task's stack painted on creation with the `lazy_stack_paint`
+/// feature, the rest is painted by the idle task.
+pub const LAZY_PAINT_INITIAL_WORDS: usize = 64;
+
+/// Number of words of each stack the idle task paints per wake up with the `lazy_stack_paint` feature.
+pub const LAZY_PAINT_STEP_WORDS: usize = 16;
+
+/// Maximum number of priority bands time-sliced with the `round_robin` feature.
+pub const MAX_ROUND_ROBIN_BANDS: usize = 4;
+
+/// Maximum number of work items waiting in the deferred work queue.
+pub const DEFERRED_WORK_LEN: usize = 16;
+
+/// Number of consecutive ticks a task may run without a context switch before it is reported as starving the other tasks.
+pub const MAX_CONTINUOUS_RUN_TICKS: u32 = 100;
+
+/// Number of consecutive ticks a task may stay blocked before it is reported as blocked too long,
+/// which usually means a missing signal or a lost wake up.
+pub const MAX_BLOCKED_TICKS: u32 = 1000;
+
+/// Action taken by the kernel when it detects a lock order violation, which could deadlock the system.
+#[derive(Clone, Copy, PartialEq)]
+pub enum DeadlockAction {
+    /// The offending call returns `KernelError::LockOrderViolation`.
+    ReturnError,
+    /// The kernel panics.
+    Panic,
+    /// The system is reset.
+    Reset,
+}
+
+#[cfg(not(any(feature = "deadlock_panic", feature = "deadlock_reset")))]
+pub const DEADLOCK_ACTION: DeadlockAction = DeadlockAction::ReturnError;
+
+#[cfg(all(feature = "deadlock_panic", not(feature = "deadlock_reset")))]
+pub const DEADLOCK_ACTION: DeadlockAction = DeadlockAction::Panic;
+
+#[cfg(feature = "deadlock_reset")]
+pub const DEADLOCK_ACTION: DeadlockAction = DeadlockAction::Reset;
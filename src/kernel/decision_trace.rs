@@ -0,0 +1,33 @@
+//! # Decision Trace Module
+//!
+//! Every decision of the context switch is recorded, and can be replayed by passing a recording back
+//! as a script: while the scripted task is ready it is picked instead of the highest priority task.
+use core::cell::RefCell;
+
+use crate::KernelError;
+use crate::system::decision_trace::DecisionTrace;
+use crate::system::scheduler::{BooleanVector, TaskId};
+use crate::utils::arch::{Mutex, critical_section};
+
+static Trace: Mutex<RefCell<DecisionTrace>> = Mutex::new(RefCell::new(DecisionTrace::new()));
+
+/// Called by the context switch with the task picked by priority, returns the task to run.
+pub fn decide(default: TaskId, ready_tasks: BooleanVector) -> TaskId {
+    critical_section(|cs_token| Trace.borrow(cs_token).borrow_mut().decide(default, ready_tasks))
+}
+
+/// Sets the script of decisions to replay and clears the recording.
+pub fn set_script(script: &[TaskId]) -> Result<(), KernelError> {
+    critical_section(|cs_token| Trace.borrow(cs_token).borrow_mut().set_script(script))
+}
+
+/// Copies the recorded decisions into `decisions` and returns the number copied.
+pub fn recorded_decisions(decisions: &mut [TaskId]) -> usize {
+    critical_section(|cs_token| {
+        let trace = Trace.borrow(cs_token).borrow();
+        let recorded = trace.recorded();
+        let count = recorded.len().min(decisions.len());
+        decisions[..count].copy_from_slice(&recorded[..count]);
+        count
+    })
+}
@@ -0,0 +1,63 @@
+//! # Deferred Work Module
+//!
+//! Lets interrupt handlers and high priority tasks move work out of their context: the work is
+//! queued by `defer` and run later, in FIFO order, by a worker task of the application's chosen priority.
+use core::cell::RefCell;
+
+use crate::KernelError;
+use crate::system::deferred_work::WorkQueue;
+use crate::system::scheduler::TaskId;
+use crate::kernel::tasks::{release, exit_task, schedule, TaskBuilder};
+use crate::utils::arch::{Mutex, critical_section};
+
+/// Global instance of the deferred work queue.
+static DeferredWork: Mutex<RefCell<WorkQueue>> = Mutex::new(RefCell::new(WorkQueue::new()));
+
+/// Creates the worker task with `priority` on `stack`. Work deferred before the worker is
+/// spawned stays queued, the worker is released right away to run it.
+pub fn spawn_worker(priority: TaskId, stack: &mut [u32]) -> Result<(), KernelError> {
+    TaskBuilder::new(worker).priority(priority).stack(stack).spawn()?;
+    let is_pending = critical_section(|cs_token| {
+        let queue = &mut DeferredWork.borrow(cs_token).borrow_mut();
+        queue.worker = Some(priority);
+        queue.len() > 0
+    });
+    if is_pending {
+        release(1 << priority);
+    }
+    Ok(())
+}
+
+/// Queues `work` to be run by the worker task and releases the worker. It can be called from any
+/// context, including interrupt handlers. Returns `KernelError::LimitExceeded` if the queue is full.
+pub fn defer(work: fn()) -> Result<(), KernelError> {
+    let worker = critical_section(|cs_token| {
+        let queue = &mut DeferredWork.borrow(cs_token).borrow_mut();
+        queue.push(work)?;
+        Ok(queue.worker)
+    })?;
+    if let Some(worker) = worker {
+        release(1 << worker);
+    }
+    Ok(())
+}
+
+/// The worker task, runs the queued work until the queue is empty. Each item is taken out of the
+/// queue before it runs, so work can be deferred from within a work item.
+fn worker() -> ! {
+    loop {
+        // The worker exits in the same critical section it finds the queue empty in, otherwise a
+        // `defer` in between would release the worker just before it exits and its work would wait.
+        let work = critical_section(|cs_token| {
+            let work = DeferredWork.borrow(cs_token).borrow_mut().pop();
+            if work.is_none() {
+                exit_task();
+            }
+            work
+        });
+        match work {
+            Some(work) => work(),
+            None => schedule(),
+        }
+    }
+}
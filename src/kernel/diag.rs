@@ -0,0 +1,153 @@
+//! # Diagnostics Module
+//!
+//! A uniform debug protocol which doesn't need a debugger to halt the CPU: the host writes a
+//! `DiagCommand` into `DIAG_BUFFER` and the diag task writes the response back into it.
+use core::ptr::{read_volatile, write_volatile};
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use crate::KernelError;
+use crate::system::diag::{DiagBuffer, DiagCommand, DiagStatus, Response};
+use crate::system::scheduler::TaskId;
+use crate::kernel::tasks::{snapshot, task_exit, TaskBuilder};
+
+#[cfg(feature = "task_monitor")]
+use {
+    crate::config::MAX_TASKS,
+    crate::kernel::task_monitor::export_metrics,
+    crate::system::task_monitor::MetricRecord,
+};
+
+#[cfg(feature = "system_logger")]
+use crate::kernel::logging;
+
+#[cfg(feature = "resources")]
+use {
+    crate::config::MAX_RESOURCES,
+    crate::system::resource::resource_table,
+};
+
+/// The buffer shared with the host, it is located through its symbol.
+#[no_mangle]
+pub static mut DIAG_BUFFER: DiagBuffer = DiagBuffer::new();
+
+/// Creates the diag task with `priority` on `stack`. Each time the task is released, it handles
+/// the pending command, if any; the application releases it, for instance from a periodic event.
+pub fn spawn_diag_task(priority: TaskId, stack: &mut [u32]) -> Result<(), KernelError> {
+    TaskBuilder::new(diag_task).priority(priority).stack(stack).spawn()?;
+    Ok(())
+}
+
+fn diag_task() -> ! {
+    loop {
+        process();
+        task_exit();
+    }
+}
+
+/// Handles the pending command in `DIAG_BUFFER`, returns false if there was none.
+pub fn process() -> bool {
+    let buffer = unsafe { &mut DIAG_BUFFER };
+    let command = unsafe { read_volatile(&buffer.command) };
+    if command == 0 {
+        return false;
+    }
+    let param = unsafe { read_volatile(&buffer.param) };
+    let mut response = Response::new(&mut buffer.response);
+    let status = match DiagCommand::from_code(command) {
+        Some(command) => dispatch(command, param, &mut response),
+        None => DiagStatus::UnknownCommand,
+    };
+    let response_len = response.len as u32;
+    unsafe {
+        write_volatile(&mut buffer.response_len, response_len);
+        write_volatile(&mut buffer.status, status as u32);
+        // The host must see the response before the command is marked as handled.
+        compiler_fence(Ordering::SeqCst);
+        write_volatile(&mut buffer.command, 0);
+    }
+    true
+}
+
+fn dispatch(command: DiagCommand, param: u32, response: &mut Response) -> DiagStatus {
+    match command {
+        DiagCommand::GetSnapshot => {
+            let snapshot = snapshot();
+            response.push(&[
+                snapshot.curr_tid,
+                snapshot.started as u32,
+                snapshot.active_tasks,
+                snapshot.blocked_tasks,
+                snapshot.is_preemptive as u32,
+            ]);
+            DiagStatus::Ok
+        }
+        DiagCommand::GetMetrics => get_metrics(response),
+        DiagCommand::DrainLog => drain_log(param, response),
+        DiagCommand::GetResourceTable => get_resource_table(response),
+    }
+}
+
+#[cfg(feature = "task_monitor")]
+fn get_metrics(response: &mut Response) -> DiagStatus {
+    let mut records = [MetricRecord::default(); MAX_TASKS];
+    let count = export_metrics(&mut records);
+    for record in records[..count].iter() {
+        let pushed = response.push(&[
+            record.tid,
+            record.max_execution_cycles,
+            record.deadline_misses,
+            record.starvation_events,
+            record.cpu_ticks,
+            record.max_blocked_ticks,
+            record.max_switch_in_cycles,
+            record.stack_watermark,
+        ]);
+        if !pushed {
+            return DiagStatus::Truncated;
+        }
+    }
+    DiagStatus::Ok
+}
+
+#[cfg(not(feature = "task_monitor"))]
+fn get_metrics(_response: &mut Response) -> DiagStatus {
+    DiagStatus::Unsupported
+}
+
+#[cfg(feature = "system_logger")]
+fn drain_log(param: u32, response: &mut Response) -> DiagStatus {
+    let mut count = 0;
+    while response.remaining() >= 4 && (param == 0 || count < param) {
+        match logging::pop() {
+            Some(event) => {
+                let words = event.event_type.to_words();
+                response.push(&[event.timestamp, words[0], words[1], words[2]]);
+                count += 1;
+            }
+            None => break,
+        }
+    }
+    DiagStatus::Ok
+}
+
+#[cfg(not(feature = "system_logger"))]
+fn drain_log(_param: u32, _response: &mut Response) -> DiagStatus {
+    DiagStatus::Unsupported
+}
+
+#[cfg(feature = "resources")]
+fn get_resource_table(response: &mut Response) -> DiagStatus {
+    let mut table = [(0, 0); MAX_RESOURCES];
+    let count = resource_table(&mut table);
+    for (ceiling, owner) in table[..count].iter() {
+        if !response.push(&[*ceiling, *owner]) {
+            return DiagStatus::Truncated;
+        }
+    }
+    DiagStatus::Ok
+}
+
+#[cfg(not(feature = "resources"))]
+fn get_resource_table(_response: &mut Response) -> DiagStatus {
+    DiagStatus::Unsupported
+}
@@ -45,6 +45,27 @@ pub fn disable(event_id: EventId) -> Result<(),KernelError> {
     })
 }
 
+/// Disables every event in a single critical section, so that no handler runs on the following
+/// ticks until the events are enabled again. Useful on a mode change or before shutting down.
+pub fn cancel_all() {
+    critical_section(|cs_token| {
+        event_manager
+            .borrow(cs_token)
+            .borrow_mut()
+            .disable_all()
+    })
+}
+
+/// Returns the number of enabled events.
+pub fn active_count() -> usize {
+    critical_section(|cs_token| {
+        event_manager
+            .borrow(cs_token)
+            .borrow()
+            .enabled_count()
+    })
+}
+
 /// Creates new Events.
 pub fn new(
     is_enabled: bool,
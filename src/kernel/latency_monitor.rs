@@ -0,0 +1,44 @@
+//! # Latency Monitor
+//!
+//! Measures the context switch in cycles of the DWT cycle counter, from the entry of PendSV to
+//! the loading of the next task. The application must enable the cycle counter, otherwise all
+//! measurements are zero.
+use core::cell::RefCell;
+
+use crate::config::CONTEXT_SWITCH_BUDGET_CYCLES;
+use crate::utils::arch::{Mutex, critical_section};
+
+/// The cycles taken by the last context switch and the maximum over all of them.
+static ContextSwitchCycles: Mutex<RefCell<(u32, u32)>> = Mutex::new(RefCell::new((0, 0)));
+
+/// Records the cycles taken by a context switch, called by PendSV. In debug builds it asserts that the
+/// switch stayed within `CONTEXT_SWITCH_BUDGET_CYCLES`, if a budget is configured.
+pub fn record_context_switch(cycles: u32) {
+    debug_assert!(
+        CONTEXT_SWITCH_BUDGET_CYCLES == 0 || cycles <= CONTEXT_SWITCH_BUDGET_CYCLES,
+        "context switch exceeded CONTEXT_SWITCH_BUDGET_CYCLES"
+    );
+    critical_section(|cs_token| {
+        let switch_cycles = &mut ContextSwitchCycles.borrow(cs_token).borrow_mut();
+        switch_cycles.0 = cycles;
+        if cycles > switch_cycles.1 {
+            switch_cycles.1 = cycles;
+        }
+    })
+}
+
+/// Returns the cycles taken by the most recent context switch.
+pub fn last_context_switch_cycles() -> u32 {
+    critical_section(|cs_token| ContextSwitchCycles.borrow(cs_token).borrow().0)
+}
+
+/// Returns the maximum cycles taken by a context switch.
+pub fn max_context_switch_cycles() -> u32 {
+    critical_section(|cs_token| ContextSwitchCycles.borrow(cs_token).borrow().1)
+}
+
+/// Returns the maximum cycles taken by a context switch and resets it, so that the next call
+/// returns the maximum over the switches since this one.
+pub fn take_max_context_switch_cycles() -> u32 {
+    critical_section(|cs_token| core::mem::replace(&mut ContextSwitchCycles.borrow(cs_token).borrow_mut().1, 0))
+}
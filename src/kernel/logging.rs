@@ -27,6 +27,23 @@ where
     })
 }
 
+/// Sets what happens to new events once the log buffer is full, by default the oldest events are overwritten.
+pub fn set_overflow_policy(policy: OverflowPolicy) {
+    critical_section(|cs_token| {
+        Logger.borrow(cs_token).borrow_mut().overflow_policy = policy;
+    })
+}
+
+/// Returns the number of events dropped because the log buffer was full, under either policy.
+pub fn dropped_count() -> u32 {
+    critical_section(|cs_token| Logger.borrow(cs_token).borrow().dropped)
+}
+
+/// Removes and returns the oldest log entry.
+pub fn pop() -> Option<LogEvent> {
+    critical_section(|cs_token| Logger.borrow(cs_token).borrow_mut().pop())
+}
+
 pub fn set_all(val: bool) {
     critical_section(|cs_token| {
         Logger.borrow(cs_token).borrow_mut().release_log = val;
@@ -39,6 +56,9 @@ pub fn set_all(val: bool) {
         Logger.borrow(cs_token).borrow_mut().message_recieve_log = val;
         Logger.borrow(cs_token).borrow_mut().semaphore_signal_log = val;
         Logger.borrow(cs_token).borrow_mut().semaphore_reset_log = val;
+        Logger.borrow(cs_token).borrow_mut().task_starvation_log = val;
+        Logger.borrow(cs_token).borrow_mut().blocked_too_long_log = val;
+        Logger.borrow(cs_token).borrow_mut().suspend_tasks_log = val;
         Logger.borrow(cs_token).borrow_mut().timer_event_log = val;
     })
 }
@@ -103,6 +123,24 @@ pub fn set_semaphore_reset(val: bool) {
     })
 }
 
+pub fn set_task_starvation(val: bool) {
+    critical_section(|cs_token| {
+        Logger.borrow(cs_token).borrow_mut().task_starvation_log = val;
+    })
+}
+
+pub fn set_blocked_too_long(val: bool) {
+    critical_section(|cs_token| {
+        Logger.borrow(cs_token).borrow_mut().blocked_too_long_log = val;
+    })
+}
+
+pub fn set_suspend_tasks(val: bool) {
+    critical_section(|cs_token| {
+        Logger.borrow(cs_token).borrow_mut().suspend_tasks_log = val;
+    })
+}
+
 pub fn set_timer_event(val: bool) {
     critical_section(|cs_token| {
         Logger.borrow(cs_token).borrow_mut().timer_event_log = val;
@@ -169,6 +207,24 @@ pub fn get_semaphore_reset() -> bool {
     })
 }
 
+pub fn get_task_starvation() -> bool {
+    critical_section(|cs_token| {
+        Logger.borrow(cs_token).borrow_mut().task_starvation_log
+    })
+}
+
+pub fn get_blocked_too_long() -> bool {
+    critical_section(|cs_token| {
+        Logger.borrow(cs_token).borrow_mut().blocked_too_long_log
+    })
+}
+
+pub fn get_suspend_tasks() -> bool {
+    critical_section(|cs_token| {
+        Logger.borrow(cs_token).borrow_mut().suspend_tasks_log
+    })
+}
+
 pub fn get_timer_event() -> bool {
     critical_section(|cs_token| {
         Logger.borrow(cs_token).borrow_mut().timer_event_log
@@ -12,4 +12,16 @@ pub mod logging;
 pub mod task_monitor;
 
 #[cfg(feature="timer")]
-pub mod timer;
\ No newline at end of file
+pub mod timer;
+
+#[cfg(feature="deferred_work")]
+pub mod deferred_work;
+
+#[cfg(feature="diag")]
+pub mod diag;
+
+#[cfg(feature="latency_monitor")]
+pub mod latency_monitor;
+
+#[cfg(feature="deterministic")]
+pub mod decision_trace;
@@ -3,10 +3,14 @@ use core::cell::RefCell;
 use crate::KernelError;
 use crate::priv_execute;
 use crate::system::scheduler::*;
-use crate::utils::arch::{svc_call,Mutex,critical_section};
+use crate::utils::arch::{svc_call,Mutex,critical_section,get_cycle_count};
 use crate::utils::arch::is_privileged;
-use crate::system::task_monitor::TaskMonitor;
+use crate::system::task_monitor::{TaskMonitor, MetricRecord};
 use crate::kernel::timer::get_time;
+use crate::kernel::tasks::{get_curr_tid, TaskManager};
+use crate::config::MAX_TASKS;
+use crate::kernel::logging;
+use crate::system::system_logger::LogEventType;
 
 static TASK_MONITOR: Mutex<RefCell<TaskMonitor>> = Mutex::new(RefCell::new(TaskMonitor::new()));
 
@@ -32,4 +36,181 @@ pub fn sweep_deadlines() {
     critical_section(|cs_token| {
         TASK_MONITOR.borrow(cs_token).borrow_mut().sweep_deadlines(get_time());
     })
-}
\ No newline at end of file
+}
+
+/// Called by the context switch when `tid` is switched out, `is_ready` is false if the task blocked or exited.
+pub fn switch_out(tid: TaskId, is_ready: bool) {
+    critical_section(|cs_token| {
+        TASK_MONITOR.borrow(cs_token).borrow_mut().switch_out(tid, is_ready, get_cycle_count());
+    })
+}
+
+/// Called by the context switch just before the next task is loaded.
+pub fn switch_in() {
+    critical_section(|cs_token| {
+        TASK_MONITOR.borrow(cs_token).borrow_mut().switch_in(get_cycle_count());
+    })
+}
+
+/// Records the cycles taken by a context switch to `tid`, called by PendSV.
+#[cfg(feature = "latency_monitor")]
+pub fn record_switch_in(tid: TaskId, cycles: u32) {
+    critical_section(|cs_token| {
+        TASK_MONITOR.borrow(cs_token).borrow_mut().record_switch_in(tid, cycles);
+    })
+}
+
+/// Returns the maximum number of cycles the task has executed for between being switched in and
+/// blocking or calling `task_exit`, preemptions by other tasks are not counted. The DWT cycle
+/// counter must be enabled by the application, otherwise this is always zero.
+pub fn max_execution_cycles(tid: TaskId) -> u32 {
+    critical_section(|cs_token| {
+        TASK_MONITOR.borrow(cs_token).borrow().get_max_execution_cycles(tid)
+    })
+}
+
+/// Called on every tick, reports the running task if it has run for `MAX_CONTINUOUS_RUN_TICKS`
+/// without a context switch, which starves all the lower priority tasks.
+pub fn sweep_run_ticks() {
+    let curr_tid = get_curr_tid();
+    let is_starving = critical_section(|cs_token| {
+        let started = TaskManager.borrow(cs_token).borrow().started;
+        TASK_MONITOR.borrow(cs_token).borrow_mut().tick_run(curr_tid, started)
+    });
+    if is_starving && logging::get_task_starvation() {
+        logging::report(LogEventType::TaskStarvation(curr_tid));
+    }
+}
+
+/// Called when the tasks in `tasks_mask` get blocked.
+pub fn on_block(tasks_mask: BooleanVector) {
+    critical_section(|cs_token| {
+        TASK_MONITOR.borrow(cs_token).borrow_mut().on_block(tasks_mask, get_time());
+    })
+}
+
+/// Called when the tasks in `tasks_mask` get unblocked.
+pub fn on_unblock(tasks_mask: BooleanVector) {
+    critical_section(|cs_token| {
+        TASK_MONITOR.borrow(cs_token).borrow_mut().on_unblock(tasks_mask, get_time());
+    })
+}
+
+/// Called when the task `old_tid` is moved to the priority `new_tid`.
+pub fn move_task(old_tid: TaskId, new_tid: TaskId) {
+    critical_section(|cs_token| {
+        TASK_MONITOR.borrow(cs_token).borrow_mut().move_task(old_tid, new_tid);
+    })
+}
+
+/// Called on every tick, reports the tasks which have stayed blocked for `MAX_BLOCKED_TICKS`. Only
+/// created tasks are reported, a priority without a task is never blocked for long.
+pub fn sweep_blocked() {
+    let expired = critical_section(|cs_token| {
+        let created = TaskManager.borrow(cs_token).borrow().created_tasks();
+        TASK_MONITOR.borrow(cs_token).borrow_mut().sweep_blocked(get_time()) & created
+    });
+    if expired != 0 && logging::get_blocked_too_long() {
+        for tid in 0..MAX_TASKS {
+            if expired & (1 << tid) != 0 {
+                logging::report(LogEventType::BlockedTooLong(tid as TaskId));
+            }
+        }
+    }
+}
+
+/// Returns the longest number of ticks the task stayed blocked continuously, including the block it
+/// is in, if any.
+pub fn max_blocked_ticks(tid: TaskId) -> u32 {
+    critical_section(|cs_token| {
+        TASK_MONITOR.borrow(cs_token).borrow().get_max_blocked_ticks(tid, get_time())
+    })
+}
+
+/// Returns the number of ticks on which the idle task was running. The system utilization over a
+/// period is `1 - idle_ticks / elapsed_ticks`.
+pub fn idle_ticks() -> u32 {
+    critical_section(|cs_token| {
+        TASK_MONITOR.borrow(cs_token).borrow().get_idle_ticks()
+    })
+}
+
+/// Returns the number of ticks on which the task was running.
+pub fn cpu_ticks(tid: TaskId) -> u32 {
+    critical_section(|cs_token| {
+        TASK_MONITOR.borrow(cs_token).borrow().get_cpu_ticks(tid)
+    })
+}
+
+/// Returns the maximum cycles taken by a context switch to the task. The switches are only measured
+/// with the `latency_monitor` feature, otherwise this is always zero.
+pub fn max_switch_in_cycles(tid: TaskId) -> u32 {
+    critical_section(|cs_token| {
+        TASK_MONITOR.borrow(cs_token).borrow().get_max_switch_in_cycles(tid)
+    })
+}
+
+/// Returns the number of times the task missed its deadline.
+pub fn deadline_misses(tid: TaskId) -> u32 {
+    critical_section(|cs_token| {
+        TASK_MONITOR.borrow(cs_token).borrow().get_deadline_misses(tid)
+    })
+}
+
+/// Returns the number of times the task was reported as starving the other tasks.
+pub fn starvation_events(tid: TaskId) -> u32 {
+    critical_section(|cs_token| {
+        TASK_MONITOR.borrow(cs_token).borrow().get_starvation_events(tid)
+    })
+}
+
+/// Returns the number of idle ticks and resets it, so that the next call returns only the ticks since this one.
+pub fn take_idle_ticks() -> u32 {
+    critical_section(|cs_token| {
+        TASK_MONITOR.borrow(cs_token).borrow_mut().take_idle_ticks()
+    })
+}
+
+/// Returns the number of deadline misses of the task and resets it.
+pub fn take_deadline_misses(tid: TaskId) -> u32 {
+    critical_section(|cs_token| {
+        TASK_MONITOR.borrow(cs_token).borrow_mut().take_deadline_misses(tid)
+    })
+}
+
+/// Returns the number of starvation events of the task and resets it.
+pub fn take_starvation_events(tid: TaskId) -> u32 {
+    critical_section(|cs_token| {
+        TASK_MONITOR.borrow(cs_token).borrow_mut().take_starvation_events(tid)
+    })
+}
+
+/// Returns the maximum execution cycles of the task and resets it, so that the next call returns
+/// the maximum over the executions completed since this one.
+pub fn take_max_execution_cycles(tid: TaskId) -> u32 {
+    critical_section(|cs_token| {
+        TASK_MONITOR.borrow(cs_token).borrow_mut().take_max_execution_cycles(tid)
+    })
+}
+
+/// Fills `records` with the metrics of every created task in the order of their TaskIds and returns
+/// the number of records written. All records are taken in a single critical section, hence are consistent
+/// with each other and with what the individual accessors return at that time.
+pub fn export_metrics(records: &mut [MetricRecord]) -> usize {
+    critical_section(|cs_token| {
+        let task_manager = TaskManager.borrow(cs_token).borrow();
+        let task_monitor = TASK_MONITOR.borrow(cs_token).borrow();
+        let curr_time = get_time();
+        let mut count = 0;
+        for tid in 0..MAX_TASKS {
+            if count == records.len() {
+                break;
+            }
+            if let Some(stack_watermark) = task_manager.stack_watermark(tid) {
+                records[count] = task_monitor.get_metrics(tid as TaskId, curr_time, stack_watermark as u32);
+                count += 1;
+            }
+        }
+        count
+    })
+}
@@ -5,11 +5,16 @@
 use core::cell::RefCell;
 
 use crate::KernelError;
+use crate::config::MAX_SHUTDOWN_HOOKS;
 use crate::priv_execute;
 use crate::system::scheduler::*;
-use crate::utils::arch::{svc_call,Mutex,critical_section,set_pendsv};
+#[cfg(feature = "resources")]
+use crate::system::resource::ceiling_blocked_tasks;
+use crate::utils::arch::{svc_call,svc_yield,Mutex,critical_section,set_pendsv,is_pendsv_pending,get_psp,enter_kernel,can_return_to_kernel};
 use crate::utils::arch::is_privileged;
 
+#[cfg(feature = "timer")]
+use crate::kernel::timer::get_time;
 #[cfg(feature = "system_logger")]
 use crate::kernel::logging; 
 #[cfg(feature = "system_logger")]
@@ -22,16 +27,59 @@ pub static TaskManager: Mutex<RefCell<Scheduler>> = Mutex::new(RefCell::new(Sche
 /// Initializes the Kernel scheduler and creates the idle task, a task that puts the CPU to sleep in a loop. 
 /// The idle task is created with zero priority; hence, it is only executed when no other task is in Ready state.
 pub fn init() -> Result<(),KernelError>{
+    #[cfg(feature = "fpu")]
+    crate::utils::arch::disable_fp_stacking();
+    #[cfg(feature = "basepri")]
+    crate::utils::arch::set_kernel_handler_priorities();
     critical_section(|cs_token| TaskManager.borrow(cs_token).borrow_mut().init() )
 }
 
 /// Starts the Kernel scheduler, which starts scheduling tasks on the CPU.
 pub fn start_kernel() -> ! {
     loop {
-        schedule();
+        preempt();
     }
 }
 
+/// Hooks run by `try_start_kernel` once the kernel has shut down.
+static ShutdownHooks: Mutex<RefCell<[Option<fn()>; MAX_SHUTDOWN_HOOKS]>> = Mutex::new(RefCell::new([None; MAX_SHUTDOWN_HOOKS]));
+
+/// Same as `start_kernel`, but returns once `request_shutdown` is called, after the task which
+/// requested it is switched out and the registered shutdown hooks have run. The tasks keep their
+/// state, calling `try_start_kernel` again resumes scheduling them.
+pub fn try_start_kernel() -> ShutdownReason {
+    enter_kernel();
+    let reason = critical_section(|cs_token| TaskManager.borrow(cs_token).borrow_mut().shutdown.take());
+    let hooks = critical_section(|cs_token| *ShutdownHooks.borrow(cs_token).borrow());
+    for hook in hooks.iter().filter_map(|hook| *hook) {
+        hook();
+    }
+    reason.unwrap_or(ShutdownReason::Requested(0))
+}
+
+/// Requests the kernel to stop scheduling and return from `try_start_kernel` with `code`.
+/// Returns `KernelError::NotFound` if the kernel was started with `start_kernel`, which never returns.
+pub fn request_shutdown(code: u32) -> Result<(), KernelError> {
+    if !can_return_to_kernel() {
+        return Err(KernelError::NotFound);
+    }
+    critical_section(|cs_token| {
+        TaskManager.borrow(cs_token).borrow_mut().shutdown = Some(ShutdownReason::Requested(code));
+    });
+    schedule();
+    Ok(())
+}
+
+/// Registers `hook` to be run by `try_start_kernel` on shutdown, hooks run in the order of registration.
+pub fn register_shutdown_hook(hook: fn()) -> Result<(), KernelError> {
+    critical_section(|cs_token| {
+        let hooks = &mut ShutdownHooks.borrow(cs_token).borrow_mut();
+        let slot = hooks.iter_mut().find(|slot| slot.is_none()).ok_or(KernelError::LimitExceeded)?;
+        *slot = Some(hook);
+        Ok(())
+    })
+}
+
 #[cfg(feature="task_monitor")]
 /// Create a new task with the configuration set as arguments passed.
 pub fn create_task(
@@ -46,6 +94,23 @@ pub fn create_task(
     })
 }
 
+#[cfg(feature="task_monitor")]
+/// Same as `create_task`, but first asserts that `stack` can hold `min_stack_words` words for the
+/// handler on top of the `CONTEXT_FRAME_WORDS` words reserved for the saved context.
+pub fn create_task_with_min_stack(
+    priority: TaskId,
+    deadline: u32,
+    stack: &mut [u32],
+    handler_fn: fn() -> !,
+    min_stack_words: usize,
+) -> Result<(), KernelError>
+{
+    if stack.len() < min_stack_words + CONTEXT_FRAME_WORDS {
+        return Err(KernelError::StackTooSmall);
+    }
+    create_task(priority, deadline, stack, handler_fn)
+}
+
 #[cfg(not(feature="task_monitor"))]
 /// Create a new task with the configuration set as arguments passed.
 pub fn create_task(
@@ -58,12 +123,214 @@ pub fn create_task(
         critical_section(|cs_token| TaskManager.borrow(cs_token).borrow_mut().create_task(priority as usize, stack, handler_fn))
     })
 }
+/// Sets aside the priorities in `tasks_mask` for the tasks created at run time by `spawn_dynamic`,
+/// which only ever uses these priorities. The idle task's priority can't be reserved.
+pub fn reserve_priorities(tasks_mask: BooleanVector) -> Result<(), KernelError> {
+    priv_execute!({
+        if tasks_mask & 1 != 0 {
+            return Err(KernelError::AccessDenied);
+        }
+        critical_section(|cs_token| {
+            TaskManager.borrow(cs_token).borrow_mut().reserved_priorities |= tasks_mask;
+        });
+        Ok(())
+    })
+}
+
+/// Creates a task at the lowest reserved priority which has no task yet and returns its priority.
+/// Like `create_task`, the task runs once it is released. Returns `KernelError::LimitExceeded` if
+/// every reserved priority is taken.
+pub fn spawn_dynamic(handler_fn: fn() -> !, stack: &'static mut [u32]) -> Result<TaskId, KernelError> {
+    priv_execute!({
+        critical_section(|cs_token| {
+            let handler = &mut TaskManager.borrow(cs_token).borrow_mut();
+            let priority = handler.free_reserved_priority().ok_or(KernelError::LimitExceeded)?;
+            #[cfg(feature="task_monitor")]
+            handler.create_task(priority, 0, stack, handler_fn)?;
+            #[cfg(not(feature="task_monitor"))]
+            handler.create_task(priority, stack, handler_fn)?;
+            Ok(priority as TaskId)
+        })
+    })
+}
+
+/// A fluent alternative to `create_task`, options which are not set keep their defaults.
+/// Core affinity has no meaning on this single core kernel and isn't an option.
+///
+/// ## Example
+/// ```rust
+/// let task = TaskBuilder::new(handler).priority(3).stack(unsafe { &mut stack3 }).periodic(50).spawn()?;
+/// ```
+pub struct TaskBuilder<'a> {
+    handler_fn: fn() -> !,
+    priority: TaskId,
+    stack: Option<&'a mut [u32]>,
+    min_stack_words: usize,
+    #[cfg(feature="task_monitor")]
+    deadline: u32,
+    name: Option<&'static str>,
+    #[cfg(feature = "timer")]
+    period: u32,
+    privileged: bool,
+}
+
+impl<'a> TaskBuilder<'a> {
+    /// Starts the description of a task running `handler_fn`. By default the task gets priority 1,
+    /// the lowest priority above the idle task, no minimum stack requirement, no deadline, no period,
+    /// and runs privileged.
+    pub fn new(handler_fn: fn() -> !) -> Self {
+        Self {
+            handler_fn,
+            priority: 1,
+            stack: None,
+            min_stack_words: 0,
+            #[cfg(feature="task_monitor")]
+            deadline: 0,
+            name: None,
+            #[cfg(feature = "timer")]
+            period: 0,
+            privileged: true,
+        }
+    }
+
+    /// Sets the priority of the task.
+    pub fn priority(mut self, priority: TaskId) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Sets the stack of the task, a task can't be spawned without one.
+    pub fn stack(mut self, stack: &'a mut [u32]) -> Self {
+        self.stack = Some(stack);
+        self
+    }
+
+    /// Sets the number of words the handler needs on top of `CONTEXT_FRAME_WORDS`.
+    pub fn min_stack_words(mut self, min_stack_words: usize) -> Self {
+        self.min_stack_words = min_stack_words;
+        self
+    }
+
+    /// Sets the relative deadline of the task, zero means the task has no deadline.
+    #[cfg(feature="task_monitor")]
+    pub fn deadline(mut self, deadline: u32) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Sets the name of the task, reported by `task_info`.
+    pub fn name(mut self, name: &'static str) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// Makes the task periodic, the kernel releases it every `period` ticks, the first time `period`
+    /// ticks after it is spawned. Zero means the task is only released explicitly.
+    #[cfg(feature = "timer")]
+    pub fn periodic(mut self, period: u32) -> Self {
+        self.period = period;
+        self
+    }
+
+    /// Sets whether the task runs in privileged thread mode. An unprivileged task reaches the kernel
+    /// only through the routines which don't require privilege, see `priv_execute!`.
+    pub fn privileged(mut self, privileged: bool) -> Self {
+        self.privileged = privileged;
+        self
+    }
+
+    /// Creates the task and returns its handle. Fails with `KernelError::StackTooSmall` if no stack
+    /// was set or if it can't hold `min_stack_words` on top of the context frame. The task and all
+    /// its options are set up in a single critical section, so it either exists fully configured or
+    /// not at all.
+    pub fn spawn(self) -> Result<TaskHandle, KernelError> {
+        let stack = self.stack.ok_or(KernelError::StackTooSmall)?;
+        if stack.len() < self.min_stack_words + CONTEXT_FRAME_WORDS {
+            return Err(KernelError::StackTooSmall);
+        }
+        let tid = self.priority;
+        priv_execute!({
+            critical_section(|cs_token| {
+                let handler = &mut TaskManager.borrow(cs_token).borrow_mut();
+                #[cfg(feature="task_monitor")]
+                handler.create_task(tid as usize, self.deadline, stack, self.handler_fn)?;
+                #[cfg(not(feature="task_monitor"))]
+                handler.create_task(tid as usize, stack, self.handler_fn)?;
+                if let Some(name) = self.name {
+                    handler.set_task_name(tid as usize, name)?;
+                }
+                handler.set_privileged(tid as usize, self.privileged)?;
+                #[cfg(feature = "timer")] {
+                    if self.period > 0 {
+                        handler.set_period(tid as usize, self.period, get_time())?;
+                    }
+                }
+                Ok(TaskHandle { tid })
+            })
+        })
+    }
+}
+
+/// A task created by `TaskBuilder::spawn`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TaskHandle {
+    tid: TaskId,
+}
+
+impl TaskHandle {
+    /// Returns the TaskId, which is the priority, of the task.
+    pub fn tid(&self) -> TaskId {
+        self.tid
+    }
+
+    /// Releases the task, see `release`.
+    pub fn release(&self) {
+        release(1 << self.tid)
+    }
+
+    /// Suspends the task, see `suspend_task`.
+    pub fn suspend(&self) -> Result<(), KernelError> {
+        suspend_task(self.tid)
+    }
+
+    /// Resumes the task, see `resume_task`.
+    pub fn resume(&self) -> Result<(), KernelError> {
+        resume_task(self.tid)
+    }
+
+    /// Returns the state of the task, see `get_task_state`.
+    pub fn state(&self) -> TaskState {
+        get_task_state(self.tid)
+    }
+}
+
+/// Called on every tick, releases the periodic tasks whose period elapsed. The switch to them
+/// happens in the `schedule` call which ends the tick.
+#[cfg(feature = "timer")]
+pub fn release_periodic() {
+    let due = critical_section(|cs_token| TaskManager.borrow(cs_token).borrow_mut().take_due_periodic(get_time()));
+    if due != 0 {
+        release_tasks(due);
+    }
+}
+
 /// This function is called from both privileged and unprivileged context.
 /// Hence if the function is called from privileged context, then `preempt()` is called.
 /// Else, the `svc_call()` is executed, this function creates the SVC exception.
 /// And the SVC handler calls schedule again. Thus, the permission level is raised to privileged via the exception.
+///
+/// Kernel routines mutate the kernel state inside a `critical_section` and call `schedule` exactly once
+/// after leaving it, so `schedule` is never called while a kernel data-structure is borrowed.
+/// Calls made before `start_kernel` are ignored, calls made while scheduling is deferred are recorded
+/// and acted upon by `resume_scheduling`.
 pub fn schedule() {
-    let is_preemptive = critical_section(|cs_token| TaskManager.borrow(cs_token).borrow_mut().is_preemptive);
+    let is_preemptive = critical_section(|cs_token| {
+        let handler = &mut TaskManager.borrow(cs_token).borrow_mut();
+        if handler.defer_switch() {
+            return false;
+        }
+        handler.is_preemptive && handler.started
+    });
     if is_preemptive {
         match is_privileged() {
             true => preempt(),
@@ -72,8 +339,144 @@ pub fn schedule() {
     } 
 }
 
+/// Called on every tick, passes the turn of a round-robin band on once the running task's quantum
+/// expires. The switch itself happens in the `schedule` call which ends the tick.
+#[cfg(feature = "round_robin")]
+pub fn round_robin_tick() {
+    critical_section(|cs_token| {
+        TaskManager.borrow(cs_token).borrow_mut().round_robin_tick();
+    })
+}
+
+/// Makes the ready tasks of `tasks_mask` share the CPU in turns of `quantum_ticks` ticks, as if they
+/// had the same priority, instead of the highest one running until it blocks. The priorities of the
+/// band must be consecutive and, like every other priority, must not include the idle task
+/// (`KernelError::AccessDenied` otherwise). A band can't overlap another one (`KernelError::Exists`),
+/// and up to `MAX_ROUND_ROBIN_BANDS` bands can be set (`KernelError::LimitExceeded`). Calling it again
+/// for the same band changes its quantum.
+///
+/// ## Example
+/// ```rust
+/// // Tasks 2, 3 and 4 run in turns of 5 ticks.
+/// enable_round_robin(0b11100, 5)?;
+/// ```
+#[cfg(feature = "round_robin")]
+pub fn enable_round_robin(tasks_mask: BooleanVector, quantum_ticks: u32) -> Result<(), KernelError> {
+    priv_execute!({
+        critical_section(|cs_token| {
+            TaskManager.borrow(cs_token).borrow_mut().enable_round_robin(tasks_mask, quantum_ticks)
+        })?;
+        schedule();
+        Ok(())
+    })
+}
+
+/// Pends the context switch, unless it is already pending. The switch picks the highest priority
+/// ready task when PendSV runs, hence any number of `schedule` calls before it lead to a single switch.
 fn preempt() {
-    set_pendsv();
+    if !is_pendsv_pending() {
+        set_pendsv();
+    }
+}
+
+/// Replaces the entry point of the task `tid`, the new handler runs once the task is restarted with
+/// `restart_task`. Returns `KernelError::AccessDenied` if `tid` is the running task.
+pub fn set_task_handler(tid: TaskId, handler: fn() -> !) -> Result<(), KernelError> {
+    priv_execute!({
+        critical_section(|cs_token| TaskManager.borrow(cs_token).borrow_mut().set_task_handler(tid as usize, handler))
+    })
+}
+
+/// Deletes the task `tid` for good, its priority is free for a new task from then on. The task is
+/// removed from the ready, blocked and suspended tasks and from the waiters of the resources, and
+/// its timeout and deadline are cancelled. A task holding a resource is rejected with
+/// `KernelError::HeldResource` rather than force unlocked, as the data it guards may be half updated;
+/// the running task and the idle task are rejected with `KernelError::AccessDenied`.
+///
+/// Semaphores, messages and the other primitives may still name the task in their masks, the releases
+/// and wakeups they make for it are ignored while no task has its priority. The check and all the
+/// clean up happen in one critical section.
+pub fn delete_task(tid: TaskId) -> Result<(), KernelError> {
+    priv_execute!({
+        critical_section(|cs_token| {
+            #[cfg(feature = "resources")] {
+                if crate::system::resource::holds_resource(tid) {
+                    return Err(KernelError::HeldResource);
+                }
+            }
+            TaskManager.borrow(cs_token).borrow_mut().delete_task(tid as usize)?;
+            #[cfg(feature = "resources")] {
+                crate::system::resource::forget_waiter(tid);
+                crate::system::resource::move_usable(tid, None);
+            }
+            #[cfg(feature = "timer")] {
+                crate::kernel::timer::clear_timeout(tid);
+            }
+            #[cfg(feature = "task_monitor")] {
+                crate::kernel::task_monitor::clear_deadline(tid);
+                crate::kernel::task_monitor::on_unblock(1 << tid);
+            }
+            Ok(())
+        })
+    })
+}
+
+/// Changes the priority of the task `old_tid` to `new_priority` and reschedules. As the priority is
+/// the TaskId, the task is moved to the slot `new_priority`, which must be free (`KernelError::Exists`
+/// otherwise), and is known by its new TaskId from then on. A task holding a resource
+/// (`KernelError::HeldResource`) or blocked (`KernelError::AccessDenied`) can't be moved, nor the idle task.
+///
+/// The ceiling of a resource is the highest priority in its task mask, hence it covers a task only if
+/// the task's priority is in the mask. The move is rejected with `KernelError::AccessDenied` if
+/// `new_priority` is outside the task mask of any resource the task has locked, see
+/// `usable_priorities`. A resource the task hasn't locked yet rejects the lock from the new priority
+/// in the same way if its mask doesn't include it.
+///
+/// The checks and the move of the scheduler, resource and monitor state happen in one critical section,
+/// so no other code sees the task half moved.
+pub fn set_task_priority(old_tid: TaskId, new_priority: TaskId) -> Result<(), KernelError> {
+    priv_execute!({
+        critical_section(|cs_token| {
+            #[cfg(feature = "resources")] {
+                if crate::system::resource::holds_resource(old_tid) {
+                    return Err(KernelError::HeldResource);
+                }
+                // An out of range priority is rejected by the scheduler below.
+                let usable = crate::system::resource::usable_priorities(old_tid);
+                if new_priority < 32 && usable & (1 << new_priority) == 0 {
+                    return Err(KernelError::AccessDenied);
+                }
+            }
+            TaskManager.borrow(cs_token).borrow_mut().set_task_priority(old_tid as usize, new_priority as usize)?;
+            #[cfg(feature = "resources")] {
+                crate::system::resource::move_usable(old_tid, Some(new_priority));
+            }
+            #[cfg(feature = "task_monitor")] {
+                crate::kernel::task_monitor::move_task(old_tid, new_priority);
+            }
+            Ok(())
+        })?;
+        schedule();
+        Ok(())
+    })
+}
+
+/// Restarts the task `tid` from the beginning of its handler, discarding its context, and releases it.
+/// The running task can't be restarted, nor a task holding a resource (`KernelError::HeldResource`).
+/// The check and the restart happen in one critical section, so the task can't lock a resource in between.
+pub fn restart_task(tid: TaskId) -> Result<(), KernelError> {
+    priv_execute!({
+        critical_section(|cs_token| {
+            #[cfg(feature = "resources")] {
+                if crate::system::resource::holds_resource(tid) {
+                    return Err(KernelError::HeldResource);
+                }
+            }
+            TaskManager.borrow(cs_token).borrow_mut().restart_task(tid as usize)
+        })?;
+        schedule();
+        Ok(())
+    })
 }
 
 /// Returns the TaskId of the currently running task in the kernel.
@@ -83,14 +486,125 @@ pub fn get_curr_tid() -> TaskId {
     })
 }
 
-/// The Kernel blocks the tasks mentioned in `tasks_mask`.
+/// Returns the number of context switches since boot, the first task loaded by `start_kernel` included.
+/// The counter wraps around on overflow.
+pub fn context_switch_count() -> u32 {
+    critical_section(|cs_token| TaskManager.borrow(cs_token).borrow().context_switches)
+}
+
+/// Returns the number of free words left on the stack of the running task, measured from its live
+/// stack pointer. A task can check it before a deep call chain or a large stack allocation.
+pub fn current_stack_remaining() -> usize {
+    let psp = get_psp();
+    critical_section(|cs_token| {
+        let handler = TaskManager.borrow(cs_token).borrow();
+        match handler.stack_base(handler.curr_tid) {
+            Some(base) => psp.saturating_sub(base) / 4,
+            None => 0,
+        }
+    })
+}
+
+/// Returns the peak number of words the task `tid` has used on its stack, `KernelError::NotFound` if
+/// there is no such task.
+pub fn stack_watermark(tid: TaskId) -> Result<usize, KernelError> {
+    critical_section(|cs_token| TaskManager.borrow(cs_token).borrow().stack_watermark(tid as usize)).ok_or(KernelError::NotFound)
+}
+
+/// Paints the next part of the stacks which are not fully painted yet, run by the idle task.
+#[cfg(feature = "lazy_stack_paint")]
+pub fn paint_stacks() {
+    critical_section(|cs_token| TaskManager.borrow(cs_token).borrow_mut().paint_step())
+}
+
+/// Returns the unused bottom of the stack of the task `tid` for other use, keeping its top `new_len`
+/// words. It fails with `KernelError::StackTooSmall` if the task's watermark exceeds `new_len`.
+///
+/// # Safety
+/// The task must never use more than `new_len` words of stack after this call.
+pub unsafe fn shrink_stack(tid: TaskId, new_len: usize) -> Result<&'static mut [u32], KernelError> {
+    critical_section(|cs_token| TaskManager.borrow(cs_token).borrow_mut().shrink_stack(tid as usize, new_len))
+}
+
+/// Returns the configuration the task `tid` was created with, `KernelError::NotFound` if there is no such task.
+pub fn task_info(tid: TaskId) -> Result<TaskInfo, KernelError> {
+    critical_section(|cs_token| TaskManager.borrow(cs_token).borrow().task_info(tid as usize)).ok_or(KernelError::NotFound)
+}
+
+/// Returns the state of the task `tid`, read in a single critical section. A suspended task which is
+/// also blocked is reported as `Suspended`.
+pub fn get_task_state(tid: TaskId) -> TaskState {
+    critical_section(|cs_token| TaskManager.borrow(cs_token).borrow().task_state(tid as usize))
+}
+
+/// Returns a consistent copy of the scheduler state.
+pub fn snapshot() -> SchedulerSnapshot {
+    critical_section(|cs_token| {
+        #[allow(unused_mut)]
+        let mut snapshot = TaskManager.borrow(cs_token).borrow().snapshot();
+        #[cfg(feature = "resources")] {
+            snapshot.system_ceiling = crate::system::resource::system_ceiling();
+        }
+        snapshot
+    })
+}
+
+/// Verifies the consistency of the scheduler and the ceiling stack. It is meant to be called from
+/// tests and debug builds at points where no kernel routine is in progress.
+pub fn check_invariants() -> Result<(), InvariantViolation> {
+    critical_section(|cs_token| TaskManager.borrow(cs_token).borrow().check_invariants())?;
+    #[cfg(feature = "resources")]
+    crate::system::resource::check_invariants()?;
+    Ok(())
+}
+
+/// Hooks run when a task gets blocked and when it gets unblocked.
+static BlockHooks: Mutex<RefCell<Option<(fn(TaskId, BlockReason), fn(TaskId))>>> = Mutex::new(RefCell::new(None));
+
+/// Registers `on_block` to be called with every task that gets blocked and the reason, and
+/// `on_unblock` with every task that gets unblocked, replacing any previous hooks. The hooks run
+/// inside the kernel's critical section, hence they must be short and must not block.
+pub fn register_block_hook(on_block: fn(TaskId, BlockReason), on_unblock: fn(TaskId)) {
+    critical_section(|cs_token| {
+        BlockHooks.borrow(cs_token).replace(Some((on_block, on_unblock)));
+    })
+}
+
+/// The Kernel blocks the tasks mentioned in `tasks_mask`. The mask is not expected to contain the
+/// currently running task, primitives which block their caller must use `block_tasks_including_self`.
 pub fn block_tasks(tasks_mask: BooleanVector) {
+    debug_assert!(
+        tasks_mask & (1 << get_curr_tid()) == 0,
+        "block_tasks would block the running task, use block_tasks_including_self"
+    );
+    block_tasks_including_self(tasks_mask, BlockReason::Other)
+}
+
+/// Same as `block_tasks`, but the mask may contain the currently running task.
+pub fn block_tasks_including_self(tasks_mask: BooleanVector, reason: BlockReason) {
     #[cfg(feature = "system_logger")] {
         if logging::get_block_tasks() {
             logging::report(LogEventType::BlockTasks(tasks_mask));
         }
     }
-    critical_section(|cs_token| TaskManager.borrow(cs_token).borrow_mut().block_tasks(tasks_mask))
+    critical_section(|cs_token| {
+        // The ceiling masks cover priorities which were never created, they are neither reported nor monitored.
+        let newly_blocked = {
+            let handler = &mut TaskManager.borrow(cs_token).borrow_mut();
+            let newly_blocked = tasks_mask & handler.created_tasks() & !handler.blocked_tasks;
+            handler.block_tasks(tasks_mask);
+            newly_blocked
+        };
+        #[cfg(feature = "task_monitor")]
+        crate::kernel::task_monitor::on_block(newly_blocked);
+        if let Some((on_block, _)) = *BlockHooks.borrow(cs_token).borrow() {
+            for tid in 0..32 {
+                if newly_blocked & (1 << tid) != 0 {
+                    on_block(tid, reason);
+                }
+            }
+        }
+    })
 }
 
 /// The Kernel unblocks the tasks mentioned in tasks_mask.
@@ -100,11 +614,43 @@ pub fn unblock_tasks(tasks_mask: BooleanVector) {
             logging::report(LogEventType::UnblockTasks(tasks_mask));
         }
     }
-    critical_section(|cs_token| TaskManager.borrow(cs_token).borrow_mut().unblock_tasks(tasks_mask))
+    critical_section(|cs_token| {
+        let unblocked = {
+            let handler = &mut TaskManager.borrow(cs_token).borrow_mut();
+            let unblocked = tasks_mask & handler.created_tasks() & handler.blocked_tasks;
+            handler.unblock_tasks(tasks_mask);
+            unblocked
+        };
+        #[cfg(feature = "task_monitor")]
+        crate::kernel::task_monitor::on_unblock(unblocked);
+        if let Some((_, on_unblock)) = *BlockHooks.borrow(cs_token).borrow() {
+            for tid in 0..32 {
+                if unblocked & (1 << tid) != 0 {
+                    on_unblock(tid);
+                }
+            }
+        }
+    })
+}
+
+/// Unblocks the tasks in `tasks_mask` which were waiting on a kernel primitive. Tasks that are
+/// also held back by the system ceiling stay blocked, they are unblocked when the ceiling is lowered.
+/// Tasks which were deleted are ignored.
+pub fn wake_tasks(tasks_mask: BooleanVector) {
+    let tasks_mask = tasks_mask & critical_section(|cs_token| TaskManager.borrow(cs_token).borrow().created_tasks());
+    #[cfg(feature = "resources")]
+    let tasks_mask = tasks_mask & !ceiling_blocked_tasks();
+    unblock_tasks(tasks_mask)
 }
 
 /// The `task_exit` function is called just after a task finishes execution. It marks the current running task as finished and then schedules the next high priority task.
 pub fn task_exit() {
+    exit_task();
+    schedule()
+}
+
+/// Same as `task_exit`, but doesn't reschedule. The caller must call `schedule` once it has left its critical section.
+pub fn exit_task() {
     critical_section(|cs_token| {
         let handler = &mut TaskManager.borrow(cs_token).borrow_mut();
         let curr_tid = handler.curr_tid;
@@ -113,12 +659,17 @@ pub fn task_exit() {
                 logging::report(LogEventType::TaskExit(curr_tid as TaskId));
             }
         }
-        handler.active_tasks &= !(1 << curr_tid as u32);
+        handler.deactivate_tasks(1 << curr_tid as u32);
     });
-    schedule()
 }
 /// The Kernel releases the tasks in the `task_mask`, these tasks transition from the waiting to the ready state.
 pub fn release(tasks_mask: BooleanVector) {
+    release_tasks(tasks_mask);
+    schedule();
+}
+
+/// Same as `release`, but doesn't reschedule. The caller must call `schedule` once it has left its critical section.
+pub fn release_tasks(tasks_mask: BooleanVector) {
     #[cfg(feature = "system_logger")] {
         if logging::get_release() {
             logging::report(LogEventType::ReleaseTasks(tasks_mask));
@@ -127,6 +678,92 @@ pub fn release(tasks_mask: BooleanVector) {
     critical_section(|cs_token| {TaskManager.borrow(cs_token).borrow_mut().release(tasks_mask)});
 }
 
+/// Suspends the task `tid`, it isn't scheduled, whatever its priority, until `resume_task`.
+pub fn suspend_task(tid: TaskId) -> Result<(), KernelError> {
+    suspend_group(1 << tid)
+}
+
+/// Resumes the task `tid` and reschedules.
+pub fn resume_task(tid: TaskId) -> Result<(), KernelError> {
+    resume_group(1 << tid)
+}
+
+/// Suspends all the tasks in `tasks_mask` in a single critical section and reschedules once. Suspended
+/// tasks keep their active and blocked state, but aren't scheduled until `resume_group`. The idle
+/// task can't be suspended (`KernelError::AccessDenied`).
+pub fn suspend_group(tasks_mask: BooleanVector) -> Result<(), KernelError> {
+    priv_execute!({
+        if tasks_mask & 1 != 0 {
+            return Err(KernelError::AccessDenied);
+        }
+        #[cfg(feature = "system_logger")] {
+            if logging::get_suspend_tasks() {
+                logging::report(LogEventType::SuspendTasks(tasks_mask));
+            }
+        }
+        critical_section(|cs_token| TaskManager.borrow(cs_token).borrow_mut().suspend_tasks(tasks_mask));
+        schedule();
+        Ok(())
+    })
+}
+
+/// Resumes all the tasks in `tasks_mask` in a single critical section and reschedules once.
+pub fn resume_group(tasks_mask: BooleanVector) -> Result<(), KernelError> {
+    priv_execute!({
+        #[cfg(feature = "system_logger")] {
+            if logging::get_suspend_tasks() {
+                logging::report(LogEventType::ResumeTasks(tasks_mask));
+            }
+        }
+        critical_section(|cs_token| TaskManager.borrow(cs_token).borrow_mut().resume_tasks(tasks_mask));
+        schedule();
+        Ok(())
+    })
+}
+
+/// Defers the context switches requested by kernel routines, such as `Resource::unlock`, until the
+/// matching `resume_scheduling`. Unlike `disable_preemption` the switches aren't lost, the last
+/// `resume_scheduling` performs a single switch if any was requested in between. Calls nest. Only the
+/// calling task's switches are deferred, the other tasks schedule as usual while it is switched out.
+pub fn defer_scheduling() {
+    critical_section(|cs_token| {
+        TaskManager.borrow(cs_token).borrow_mut().defer_scheduling();
+    })
+}
+
+/// Ends a `defer_scheduling` region, the outermost one reschedules if a switch was requested within it.
+pub fn resume_scheduling() {
+    let is_pending = critical_section(|cs_token| TaskManager.borrow(cs_token).borrow_mut().resume_scheduling());
+    if is_pending {
+        schedule();
+    }
+}
+
+/// Gives up the CPU to a higher priority task which is ready but was not switched to yet, for
+/// instance while preemption is disabled or scheduling is deferred; the switch happens regardless.
+/// The switch is reported as `SwitchReason::Yielded`, nothing happens if no other task is due.
+pub fn task_yield() {
+    let is_due = critical_section(|cs_token| {
+        let handler = &mut TaskManager.borrow(cs_token).borrow_mut();
+        let is_due = handler.started && handler.get_next_tid() != handler.curr_tid;
+        if is_due {
+            handler.switch_hint = Some(SwitchReason::Yielded);
+        }
+        is_due
+    });
+    if is_due {
+        match is_privileged() {
+            true => preempt(),
+            false => svc_yield(),
+        };
+    }
+}
+
+/// Returns why the most recent context switch happened, `None` before the first switch between tasks.
+pub fn last_switch_reason() -> Option<SwitchReason> {
+    critical_section(|cs_token| TaskManager.borrow(cs_token).borrow().last_switch_reason)
+}
+
 /// Enable preemptive scheduling
 pub fn enable_preemption() {
     critical_section(|cs_token| {
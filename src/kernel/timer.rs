@@ -4,10 +4,15 @@ use core::cell::RefCell;
 use crate::KernelError;
 use crate::priv_execute;
 use crate::system::scheduler::*;
+use crate::config::MAX_TASKS;
+use crate::kernel::tasks::wake_tasks;
 use crate::utils::arch::{svc_call,Mutex,critical_section,SystClkSource,Peripherals};
 use crate::utils::arch::is_privileged;
 
 static SystemTimer: Mutex<RefCell<u32>> = Mutex::new(RefCell::new(0));
+
+/// Timeouts of the tasks in a timed wait: the remaining ticks of each task, and the tasks whose timeout expired.
+static Timeouts: Mutex<RefCell<([Option<u32>; MAX_TASKS], BooleanVector)>> = Mutex::new(RefCell::new(([None; MAX_TASKS], 0)));
  
 // TODO: on timer expire raise an event or make a log entry
 
@@ -24,6 +29,57 @@ pub fn get_time() -> u32 {
     })
 }
 
+/// Starts a timeout of `ticks` ticks for the task `tid`, on expiry the task is woken up and
+/// `take_timed_out` returns true for it.
+pub fn set_timeout(tid: TaskId, ticks: u32) {
+    critical_section(|cs_token| {
+        let timeouts = &mut Timeouts.borrow(cs_token).borrow_mut();
+        timeouts.0[tid as usize] = Some(ticks);
+        timeouts.1 &= !(1 << tid);
+    })
+}
+
+/// Cancels the timeout of the task `tid`.
+pub fn clear_timeout(tid: TaskId) {
+    critical_section(|cs_token| {
+        let timeouts = &mut Timeouts.borrow(cs_token).borrow_mut();
+        timeouts.0[tid as usize] = None;
+        timeouts.1 &= !(1 << tid);
+    })
+}
+
+/// Returns true if the timeout of the task `tid` expired, and resets it.
+pub fn take_timed_out(tid: TaskId) -> bool {
+    critical_section(|cs_token| {
+        let timeouts = &mut Timeouts.borrow(cs_token).borrow_mut();
+        let timed_out = timeouts.1 & (1 << tid) != 0;
+        timeouts.1 &= !(1 << tid);
+        timed_out
+    })
+}
+
+/// Counts down the timeouts, called on every tick. The tasks whose timeout expired are woken up.
+pub fn sweep_timeouts() {
+    critical_section(|cs_token| {
+        let timeouts = &mut Timeouts.borrow(cs_token).borrow_mut();
+        let mut expired: BooleanVector = 0;
+        for tid in 0..MAX_TASKS {
+            if let Some(ticks) = timeouts.0[tid] {
+                if ticks <= 1 {
+                    timeouts.0[tid] = None;
+                    expired |= 1 << tid;
+                } else {
+                    timeouts.0[tid] = Some(ticks - 1);
+                }
+            }
+        }
+        timeouts.1 |= expired;
+        if expired != 0 {
+            wake_tasks(expired);
+        }
+    })
+}
+
 /// Starts the Kernel timer. Timing event manager, logging and task monitor
 /// are heavily dependent on the timer.
 pub fn start_timer(peripherals: &mut Peripherals, tick_interval: u32) {
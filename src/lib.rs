@@ -48,6 +48,8 @@ pub mod events {
     pub use crate::kernel::events::enable;
     pub use crate::kernel::events::disable;
     pub use crate::kernel::events::new;
+    pub use crate::kernel::events::cancel_all;
+    pub use crate::kernel::events::active_count;
 }
 
 /// Kernel timer management.
@@ -55,25 +57,143 @@ pub mod events {
 pub mod timer {
     pub use crate::kernel::timer::start_timer;
 }
+/// Kernel routines which move work out of interrupt handlers into a worker task.
+#[cfg(feature = "deferred_work")]
+pub mod deferred_work {
+    pub use crate::kernel::deferred_work::defer;
+    pub use crate::kernel::deferred_work::spawn_worker;
+}
+
+/// Kernel routines which answer the diagnostic commands of a host.
+#[cfg(feature = "diag")]
+pub mod diag {
+    pub use crate::kernel::diag::process;
+    pub use crate::kernel::diag::spawn_diag_task;
+    pub use crate::kernel::diag::DIAG_BUFFER;
+    pub use crate::system::diag::{DiagBuffer, DiagCommand, DiagStatus};
+}
+
+/// Kernel routines which report the measured context switch latency.
+#[cfg(feature = "latency_monitor")]
+pub mod latency_monitor {
+    pub use crate::kernel::latency_monitor::last_context_switch_cycles;
+    pub use crate::kernel::latency_monitor::max_context_switch_cycles;
+    pub use crate::kernel::latency_monitor::take_max_context_switch_cycles;
+}
+
+/// Kernel routines which record and replay the scheduling decisions.
+#[cfg(feature = "deterministic")]
+pub mod decision_trace {
+    pub use crate::kernel::decision_trace::set_script;
+    pub use crate::kernel::decision_trace::recorded_decisions;
+}
+
 /// Kernel primitives which assist application development.
 pub mod primitives {
+    pub use crate::system::barrier::Barrier;
+    pub use crate::system::binary_semaphore::BinarySemaphore;
+    pub use crate::system::counting_semaphore::CountingSemaphore;
+    pub use crate::system::event_group::{EventFlag, EventGroup};
     pub use crate::system::message::Message;
+    #[cfg(feature = "resources")]
     pub use crate::system::resource::Resource;
+    #[cfg(feature = "resources")]
+    pub use crate::system::resource::{ResourceFor, TaskContext};
+    #[cfg(feature = "resources")]
+    pub use crate::system::resource::ResourceGuard;
+    #[cfg(feature = "resource_stats")]
+    pub use crate::system::resource::ResourceStats;
+    #[cfg(feature = "resources")]
+    pub use crate::system::rw_resource::RwResource;
+    #[cfg(feature = "resources")]
+    pub use crate::system::mutex::{Mutex, MutexGuard};
     pub use crate::system::semaphore::Semaphore;
+    pub use crate::system::task_local::TaskLocal;
 }
 
 /// Kernel routines which assist in Task management.
 pub mod tasks {
     pub use crate::kernel::tasks::enable_preemption;
     pub use crate::kernel::tasks::disable_preemption;
+    pub use crate::kernel::tasks::defer_scheduling;
+    pub use crate::kernel::tasks::resume_scheduling;
     pub use crate::kernel::tasks::create_task;
+    pub use crate::kernel::tasks::{TaskBuilder, TaskHandle};
+    pub use crate::kernel::tasks::reserve_priorities;
+    pub use crate::kernel::tasks::spawn_dynamic;
+    pub use crate::kernel::tasks::check_invariants;
+    pub use crate::system::scheduler::InvariantViolation;
     pub use crate::kernel::tasks::init;
     pub use crate::kernel::tasks::get_curr_tid;
+    pub use crate::kernel::tasks::context_switch_count;
+    pub use crate::kernel::tasks::task_yield;
+    #[cfg(feature = "round_robin")]
+    pub use crate::kernel::tasks::enable_round_robin;
+    pub use crate::kernel::tasks::last_switch_reason;
+    pub use crate::system::scheduler::SwitchReason;
     pub use crate::kernel::tasks::release;
     pub use crate::kernel::tasks::start_kernel;
+    pub use crate::kernel::tasks::try_start_kernel;
+    pub use crate::kernel::tasks::request_shutdown;
+    pub use crate::kernel::tasks::register_shutdown_hook;
+    pub use crate::kernel::tasks::register_block_hook;
+    pub use crate::system::scheduler::BlockReason;
+    pub use crate::system::scheduler::ShutdownReason;
     pub use crate::kernel::tasks::task_exit;
+    pub use crate::kernel::tasks::set_task_handler;
+    pub use crate::kernel::tasks::restart_task;
+    pub use crate::kernel::tasks::set_task_priority;
+    pub use crate::kernel::tasks::delete_task;
+    pub use crate::kernel::tasks::suspend_task;
+    pub use crate::kernel::tasks::resume_task;
+    pub use crate::kernel::tasks::suspend_group;
+    pub use crate::kernel::tasks::resume_group;
+    pub use crate::kernel::tasks::current_stack_remaining;
+    pub use crate::kernel::tasks::stack_watermark;
+    pub use crate::kernel::tasks::shrink_stack;
+    pub use crate::kernel::tasks::snapshot;
+    pub use crate::system::scheduler::{SchedulerSnapshot, SnapshotDiff};
+    pub use crate::kernel::tasks::task_info;
+    pub use crate::system::scheduler::TaskInfo;
+    pub use crate::kernel::tasks::get_task_state;
+    pub use crate::system::scheduler::TaskState;
+    #[cfg(feature = "resources")]
+    pub use crate::system::resource::who_blocks;
+    #[cfg(feature = "resources")]
+    pub use crate::system::resource::usable_priorities;
+    #[cfg(feature = "resources")]
+    pub use crate::system::resource::worst_case_blocking;
+    pub use crate::system::scheduler::CONTEXT_FRAME_WORDS;
+    #[cfg(feature="task_monitor")]
+    pub use crate::kernel::tasks::create_task_with_min_stack;
     #[cfg(feature="task_monitor")]
     pub use crate::kernel::task_monitor::set_handler as set_deadline_exceed_handler;
+    #[cfg(feature="task_monitor")]
+    pub use crate::kernel::task_monitor::max_execution_cycles;
+    #[cfg(feature="task_monitor")]
+    pub use crate::kernel::task_monitor::deadline_misses;
+    #[cfg(feature="task_monitor")]
+    pub use crate::kernel::task_monitor::starvation_events;
+    #[cfg(feature="task_monitor")]
+    pub use crate::kernel::task_monitor::idle_ticks;
+    #[cfg(feature="task_monitor")]
+    pub use crate::kernel::task_monitor::max_blocked_ticks;
+    #[cfg(feature="task_monitor")]
+    pub use crate::kernel::task_monitor::cpu_ticks;
+    #[cfg(feature="task_monitor")]
+    pub use crate::kernel::task_monitor::max_switch_in_cycles;
+    #[cfg(feature="task_monitor")]
+    pub use crate::kernel::task_monitor::take_idle_ticks;
+    #[cfg(feature="task_monitor")]
+    pub use crate::kernel::task_monitor::take_deadline_misses;
+    #[cfg(feature="task_monitor")]
+    pub use crate::kernel::task_monitor::take_starvation_events;
+    #[cfg(feature="task_monitor")]
+    pub use crate::kernel::task_monitor::take_max_execution_cycles;
+    #[cfg(feature="task_monitor")]
+    pub use crate::kernel::task_monitor::export_metrics;
+    #[cfg(feature="task_monitor")]
+    pub use crate::system::task_monitor::MetricRecord;
 }
 
 #[cfg(feature="system_logger")]
@@ -92,7 +212,15 @@ pub mod logging {
     pub use crate::kernel::logging::set_semaphore_signal;
     pub use crate::kernel::logging::set_semaphore_reset;
     pub use crate::kernel::logging::set_timer_event;
+    pub use crate::kernel::logging::set_task_starvation;
+    pub use crate::kernel::logging::set_blocked_too_long;
+    pub use crate::kernel::logging::set_suspend_tasks;
     pub use crate::system::system_logger::LogEvent;
+    pub use crate::kernel::logging::set_overflow_policy;
+    pub use crate::kernel::logging::dropped_count;
+    pub use crate::system::system_logger::OverflowPolicy;
+    #[cfg(feature = "resources")]
+    pub use crate::system::resource::dump_ceiling_stack;
 }
 
 #[cfg(feature = "alloc")]
@@ -56,3 +56,33 @@ macro_rules! priv_execute {
         }
     };
 }
+
+
+/// `define_events!` defines an enum whose variants name the events of an `EventGroup`, each
+/// variant maps to a bit in the order of declaration. At most 32 events can be defined.
+/// ## Example
+/// ```rust
+/// define_events! {
+///     enum AppEvent { DataReady, Error, Shutdown }
+/// }
+/// static APP_EVENTS: EventGroup<AppEvent> = EventGroup::new();
+/// APP_EVENTS.set(AppEvent::DataReady);
+/// ```
+#[macro_export]
+macro_rules! define_events {
+    ($vis: vis enum $name: ident { $($variant: ident),* $(,)? }) => {
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        #[repr(u32)]
+        $vis enum $name {
+            $($variant),*
+        }
+
+        const _: u32 = 32 - [$(stringify!($variant)),*].len() as u32;
+
+        impl $crate::primitives::EventFlag for $name {
+            fn mask(self) -> u32 {
+                1 << (self as u32)
+            }
+        }
+    };
+}
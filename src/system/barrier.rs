@@ -0,0 +1,68 @@
+//! # Barrier
+//!
+//! A latch which, once opened, lets all waiting and arriving tasks proceed until it is reset.
+use core::cell::RefCell;
+
+use crate::system::scheduler::{BlockReason, BooleanVector};
+use crate::kernel::tasks::{block_tasks_including_self, get_curr_tid, schedule, wake_tasks};
+use crate::utils::arch::critical_section;
+
+/// A manual-reset event shared by any number of tasks.
+pub struct Barrier {
+    /// True while the barrier is open.
+    is_open: RefCell<bool>,
+    /// A boolean vector of the tasks currently blocked in `wait`.
+    waiters: RefCell<BooleanVector>,
+}
+
+impl Barrier {
+    /// Initializes a new barrier in the closed state.
+    pub const fn new() -> Self {
+        Self {
+            is_open: RefCell::new(false),
+            waiters: RefCell::new(0),
+        }
+    }
+
+    /// Blocks the currently running task until the barrier is opened. Returns immediately if it is already open.
+    pub fn wait(&'static self) {
+        loop {
+            let is_open = critical_section(|_| {
+                if *self.is_open.borrow() {
+                    return true;
+                }
+                let curr_tid_mask = 1 << get_curr_tid();
+                *self.waiters.borrow_mut() |= curr_tid_mask;
+                block_tasks_including_self(curr_tid_mask, BlockReason::Barrier);
+                false
+            });
+            if is_open {
+                return;
+            }
+            schedule();
+        }
+    }
+
+    /// Opens the barrier and releases all the tasks waiting on it.
+    pub fn open(&'static self) {
+        critical_section(|_| {
+            *self.is_open.borrow_mut() = true;
+            wake_tasks(self.waiters.replace(0));
+        });
+        schedule();
+    }
+
+    /// Closes the barrier, tasks calling `wait` after this block until the next `open`.
+    pub fn reset(&'static self) {
+        critical_section(|_| {
+            *self.is_open.borrow_mut() = false;
+        })
+    }
+
+    /// Returns true if the barrier is currently open.
+    pub fn is_open(&'static self) -> bool {
+        critical_section(|_| *self.is_open.borrow())
+    }
+}
+
+unsafe impl Sync for Barrier {}
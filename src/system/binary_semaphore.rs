@@ -0,0 +1,80 @@
+//! # Binary Semaphore
+//!
+//! A semaphore which is either available or taken, for handing off control between tasks.
+use core::cell::RefCell;
+
+use crate::system::scheduler::{BlockReason, BooleanVector};
+use crate::kernel::tasks::{block_tasks_including_self, get_curr_tid, release_tasks, schedule, wake_tasks};
+use crate::utils::arch::{critical_section, get_msb};
+
+/// A semaphore holding at most one unit.
+pub struct BinarySemaphore {
+    /// True if the semaphore can be taken.
+    available: RefCell<bool>,
+    /// It is a boolean vector that corresponds to the tasks that are to be released when the semaphore is given.
+    pub tasks: BooleanVector,
+    /// It is a boolean vector of the tasks currently blocked in `take`.
+    waiters: RefCell<BooleanVector>,
+}
+
+impl BinarySemaphore {
+    /// Initializes a new binary semaphore, the first `take` succeeds immediately if `initially_available` is true.
+    pub const fn new(tasks: BooleanVector, initially_available: bool) -> Self {
+        Self {
+            available: RefCell::new(initially_available),
+            tasks,
+            waiters: RefCell::new(0),
+        }
+    }
+
+    /// Makes the semaphore available, releases its tasks and wakes the highest priority task waiting
+    /// in `take`. Giving an already available semaphore does nothing.
+    pub fn give(&'static self) {
+        let is_given = critical_section(|_| {
+            if self.available.replace(true) {
+                return false;
+            }
+            release_tasks(self.tasks);
+            let waiters: &mut BooleanVector = &mut self.waiters.borrow_mut();
+            if let Some(tid) = get_msb(*waiters) {
+                *waiters &= !(1 << tid);
+                wake_tasks(1 << tid);
+            }
+            true
+        });
+        if is_given {
+            schedule();
+        }
+    }
+
+    /// Takes the semaphore, blocking the running task until it is given if it isn't available.
+    pub fn take(&'static self) {
+        loop {
+            let is_taken = critical_section(|_| {
+                if self.available.replace(false) {
+                    return true;
+                }
+                let curr_tid_mask = 1 << get_curr_tid();
+                *self.waiters.borrow_mut() |= curr_tid_mask;
+                block_tasks_including_self(curr_tid_mask, BlockReason::Semaphore);
+                false
+            });
+            if is_taken {
+                return;
+            }
+            schedule();
+        }
+    }
+
+    /// Takes the semaphore if it is available and returns true, else returns false immediately without blocking.
+    pub fn try_take(&'static self) -> bool {
+        critical_section(|_| self.available.replace(false))
+    }
+
+    /// Returns true if the semaphore can be taken.
+    pub fn is_available(&'static self) -> bool {
+        critical_section(|_| *self.available.borrow())
+    }
+}
+
+unsafe impl Sync for BinarySemaphore {}
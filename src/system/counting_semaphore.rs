@@ -0,0 +1,91 @@
+//! # Counting Semaphore
+//!
+//! A semaphore holding a bounded number of units, for resource pools and producer/consumer buffering.
+use core::cell::RefCell;
+
+use crate::system::scheduler::{BlockReason, BooleanVector};
+use crate::kernel::tasks::{block_tasks_including_self, get_curr_tid, release_tasks, schedule, wake_tasks};
+use crate::utils::arch::{critical_section, get_msb};
+
+/// Counts the units given by producers and taken by consumers.
+pub struct CountingSemaphore {
+    /// Number of units currently available.
+    count: RefCell<u32>,
+    /// The count saturates at this value.
+    max: u32,
+    /// It is a boolean vector that corresponds to the tasks that are to be released when a unit is posted.
+    pub tasks: BooleanVector,
+    /// It is a boolean vector of the tasks currently blocked in `wait`.
+    waiters: RefCell<BooleanVector>,
+}
+
+impl CountingSemaphore {
+    /// Initializes a new counting semaphore with `initial` units, `initial` is clamped to `max`.
+    pub const fn new(tasks: BooleanVector, initial: u32, max: u32) -> Self {
+        let initial = if initial > max { max } else { initial };
+        Self {
+            count: RefCell::new(initial),
+            max,
+            tasks,
+            waiters: RefCell::new(0),
+        }
+    }
+
+    /// Adds a unit, saturating at `max`, releases the tasks of the semaphore and wakes the highest
+    /// priority task waiting in `wait`, which takes the unit.
+    pub fn post(&'static self) {
+        critical_section(|_| {
+            let count: &mut u32 = &mut self.count.borrow_mut();
+            if *count < self.max {
+                *count += 1;
+            }
+            release_tasks(self.tasks);
+            let waiters: &mut BooleanVector = &mut self.waiters.borrow_mut();
+            if let Some(tid) = get_msb(*waiters) {
+                *waiters &= !(1 << tid);
+                wake_tasks(1 << tid);
+            }
+        });
+        schedule();
+    }
+
+    /// Takes a unit, blocking the running task until one is posted if none is available.
+    pub fn wait(&'static self) {
+        loop {
+            let is_taken = critical_section(|_| {
+                let count: &mut u32 = &mut self.count.borrow_mut();
+                if *count > 0 {
+                    *count -= 1;
+                    return true;
+                }
+                let curr_tid_mask = 1 << get_curr_tid();
+                *self.waiters.borrow_mut() |= curr_tid_mask;
+                block_tasks_including_self(curr_tid_mask, BlockReason::Semaphore);
+                false
+            });
+            if is_taken {
+                return;
+            }
+            schedule();
+        }
+    }
+
+    /// Takes a unit if one is available and returns true, else returns false immediately without blocking.
+    pub fn try_acquire(&'static self) -> bool {
+        critical_section(|_| {
+            let count: &mut u32 = &mut self.count.borrow_mut();
+            if *count > 0 {
+                *count -= 1;
+                return true;
+            }
+            false
+        })
+    }
+
+    /// Returns the number of units currently available.
+    pub fn available(&'static self) -> u32 {
+        critical_section(|_| *self.count.borrow())
+    }
+}
+
+unsafe impl Sync for CountingSemaphore {}
@@ -0,0 +1,64 @@
+//! # Decision Trace
+//!
+//! Records the scheduling decisions and replays a script of them, for reproducing a task interleaving.
+use crate::config::DECISION_TRACE_LEN;
+use crate::system::scheduler::{BooleanVector, TaskId};
+use crate::KernelError;
+
+pub struct DecisionTrace {
+    /// The tasks picked by the scheduler, in order.
+    recorded: [TaskId; DECISION_TRACE_LEN],
+    recorded_len: usize,
+    /// The tasks the scheduler is told to pick, in order.
+    script: [TaskId; DECISION_TRACE_LEN],
+    script_len: usize,
+    /// Position of the next decision in `script`.
+    script_pos: usize,
+}
+
+impl DecisionTrace {
+    pub const fn new() -> Self {
+        Self {
+            recorded: [0; DECISION_TRACE_LEN],
+            recorded_len: 0,
+            script: [0; DECISION_TRACE_LEN],
+            script_len: 0,
+            script_pos: 0,
+        }
+    }
+
+    /// Returns the task to run: the next scripted task if it is ready, else `default`, which is the task
+    /// picked by priority. The decision is recorded, decisions past `DECISION_TRACE_LEN` are not.
+    pub fn decide(&mut self, default: TaskId, ready_tasks: BooleanVector) -> TaskId {
+        let mut next = default;
+        if self.script_pos < self.script_len {
+            let scripted = self.script[self.script_pos];
+            if ready_tasks & (1 << scripted) != 0 {
+                next = scripted;
+                self.script_pos += 1;
+            }
+        }
+        if self.recorded_len < DECISION_TRACE_LEN {
+            self.recorded[self.recorded_len] = next;
+            self.recorded_len += 1;
+        }
+        next
+    }
+
+    /// Replaces the script with `script` and clears the recorded decisions.
+    pub fn set_script(&mut self, script: &[TaskId]) -> Result<(), KernelError> {
+        if script.len() > DECISION_TRACE_LEN {
+            return Err(KernelError::LimitExceeded);
+        }
+        self.script[..script.len()].copy_from_slice(script);
+        self.script_len = script.len();
+        self.script_pos = 0;
+        self.recorded_len = 0;
+        Ok(())
+    }
+
+    /// Returns the decisions recorded so far.
+    pub fn recorded(&self) -> &[TaskId] {
+        &self.recorded[..self.recorded_len]
+    }
+}
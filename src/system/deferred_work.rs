@@ -0,0 +1,55 @@
+//! # Deferred Work
+//!
+//! Defines the queue of work deferred by interrupt handlers and tasks to the worker task.
+use crate::config::DEFERRED_WORK_LEN;
+use crate::system::scheduler::TaskId;
+use crate::KernelError;
+
+/// A FIFO of the work items waiting to be run by the worker task.
+pub struct WorkQueue {
+    /// Circular buffer of the work items.
+    queue: [Option<fn()>; DEFERRED_WORK_LEN],
+    /// Index of the oldest work item.
+    head: usize,
+    /// Number of work items in the queue.
+    len: usize,
+    /// The task which runs the work items, `None` until it is spawned.
+    pub worker: Option<TaskId>,
+}
+
+impl WorkQueue {
+    pub const fn new() -> Self {
+        Self {
+            queue: [None; DEFERRED_WORK_LEN],
+            head: 0,
+            len: 0,
+            worker: None,
+        }
+    }
+
+    /// Appends `work` to the queue, returns `KernelError::LimitExceeded` if the queue is full.
+    pub fn push(&mut self, work: fn()) -> Result<(), KernelError> {
+        if self.len == DEFERRED_WORK_LEN {
+            return Err(KernelError::LimitExceeded);
+        }
+        self.queue[(self.head + self.len) % DEFERRED_WORK_LEN] = Some(work);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Returns the number of work items in the queue.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Removes and returns the oldest work item.
+    pub fn pop(&mut self) -> Option<fn()> {
+        if self.len == 0 {
+            return None;
+        }
+        let work = self.queue[self.head].take();
+        self.head = (self.head + 1) % DEFERRED_WORK_LEN;
+        self.len -= 1;
+        work
+    }
+}
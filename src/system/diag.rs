@@ -0,0 +1,97 @@
+//! # Diagnostics
+//!
+//! Defines the request/response buffer through which a host queries the kernel state.
+use crate::config::DIAG_RESPONSE_WORDS;
+
+/// Commands a host can write into `DiagBuffer::command`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(u32)]
+pub enum DiagCommand {
+    /// Responds with `[curr_tid, started, active_tasks, blocked_tasks, is_preemptive]`.
+    GetSnapshot = 1,
+    /// Responds with `[tid, max_execution_cycles, deadline_misses, starvation_events, cpu_ticks,
+    /// max_blocked_ticks, max_switch_in_cycles, stack_watermark]` for every task, see `MetricRecord`.
+    GetMetrics = 2,
+    /// Removes up to `param` log entries (as many as fit if zero) and responds with
+    /// `[timestamp, code, first argument, second argument]` for each.
+    DrainLog = 3,
+    /// Responds with `[ceiling, owner]` for each locked resource, from the bottom of the ceiling stack.
+    GetResourceTable = 4,
+}
+
+impl DiagCommand {
+    /// Returns the command corresponding to `code`.
+    pub fn from_code(code: u32) -> Option<Self> {
+        match code {
+            1 => Some(DiagCommand::GetSnapshot),
+            2 => Some(DiagCommand::GetMetrics),
+            3 => Some(DiagCommand::DrainLog),
+            4 => Some(DiagCommand::GetResourceTable),
+            _ => None,
+        }
+    }
+}
+
+/// Result of a command, written into `DiagBuffer::status`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(u32)]
+pub enum DiagStatus {
+    Ok = 0,
+    /// The command code is not a `DiagCommand`.
+    UnknownCommand = 1,
+    /// The command needs a kernel feature which is not enabled.
+    Unsupported = 2,
+    /// The response didn't fit in `DIAG_RESPONSE_WORDS`, only the entries in `response` were written.
+    Truncated = 3,
+}
+
+/// The buffer shared with the host. The host writes `param` and then a non-zero `command`; the
+/// kernel writes the response words, `response_len` and `status`, and then resets `command` to zero.
+#[repr(C)]
+pub struct DiagBuffer {
+    pub command: u32,
+    pub param: u32,
+    pub status: u32,
+    /// Number of valid words in `response`.
+    pub response_len: u32,
+    pub response: [u32; DIAG_RESPONSE_WORDS],
+}
+
+impl DiagBuffer {
+    pub const fn new() -> Self {
+        Self {
+            command: 0,
+            param: 0,
+            status: 0,
+            response_len: 0,
+            response: [0; DIAG_RESPONSE_WORDS],
+        }
+    }
+}
+
+/// Appends words to a response, words which don't fit are dropped.
+pub struct Response<'a> {
+    words: &'a mut [u32],
+    pub len: usize,
+}
+
+impl<'a> Response<'a> {
+    pub fn new(words: &'a mut [u32]) -> Self {
+        Self { words, len: 0 }
+    }
+
+    /// Returns the number of words which can still be appended.
+    pub fn remaining(&self) -> usize {
+        self.words.len() - self.len
+    }
+
+    /// Appends all of `words` if they fit and returns true, else appends nothing and returns false.
+    pub fn push(&mut self, words: &[u32]) -> bool {
+        if words.len() > self.remaining() {
+            return false;
+        }
+        self.words[self.len..self.len + words.len()].copy_from_slice(words);
+        self.len += words.len();
+        true
+    }
+}
@@ -83,6 +83,18 @@ impl EventTable
         Ok(())
     }
 
+    /// Disables all the events.
+    pub fn disable_all(&mut self) {
+        for event in self.events[..self.curr].iter_mut().flatten() {
+            event.is_enabled = false;
+        }
+    }
+
+    /// Returns the number of enabled events.
+    pub fn enabled_count(&self) -> usize {
+        self.events[..self.curr].iter().flatten().filter(|event| event.is_enabled).count()
+    }
+
     /// Creates a new event.
     pub fn create(
         &mut self,
@@ -0,0 +1,77 @@
+//! # Event Group
+//!
+//! A set of named event flags which tasks can set, clear and wait on.
+use core::cell::RefCell;
+
+use crate::system::scheduler::{BlockReason, BooleanVector};
+use crate::kernel::tasks::{block_tasks_including_self, get_curr_tid, schedule, wake_tasks};
+use crate::utils::arch::critical_section;
+
+/// Implemented by the enums generated with `define_events!`, maps an event to its bit in an `EventGroup`.
+pub trait EventFlag: Copy {
+    /// Returns the bit mask of the event.
+    fn mask(self) -> u32;
+}
+
+/// Holds the flags of up to 32 events of type `E`.
+pub struct EventGroup<E: EventFlag> {
+    /// The bits of the events currently set.
+    flags: RefCell<u32>,
+    /// A boolean vector of the tasks currently blocked in `wait`.
+    waiters: RefCell<BooleanVector>,
+    events: core::marker::PhantomData<E>,
+}
+
+impl<E: EventFlag> EventGroup<E> {
+    /// Initializes a new event group with all events cleared.
+    pub const fn new() -> Self {
+        Self {
+            flags: RefCell::new(0),
+            waiters: RefCell::new(0),
+            events: core::marker::PhantomData,
+        }
+    }
+
+    /// Sets `event` and releases the tasks waiting on the group.
+    pub fn set(&'static self, event: E) {
+        critical_section(|_| {
+            *self.flags.borrow_mut() |= event.mask();
+            wake_tasks(self.waiters.replace(0));
+        });
+        schedule();
+    }
+
+    /// Clears `event`.
+    pub fn clear(&'static self, event: E) {
+        critical_section(|_| {
+            *self.flags.borrow_mut() &= !event.mask();
+        })
+    }
+
+    /// Returns true if `event` is set.
+    pub fn is_set(&'static self, event: E) -> bool {
+        critical_section(|_| *self.flags.borrow() & event.mask() != 0)
+    }
+
+    /// Blocks the currently running task until `event` is set. Returns immediately if it is already
+    /// set, the event is not cleared.
+    pub fn wait(&'static self, event: E) {
+        loop {
+            let is_set = critical_section(|_| {
+                if *self.flags.borrow() & event.mask() != 0 {
+                    return true;
+                }
+                let curr_tid_mask = 1 << get_curr_tid();
+                *self.waiters.borrow_mut() |= curr_tid_mask;
+                block_tasks_including_self(curr_tid_mask, BlockReason::EventGroup);
+                false
+            });
+            if is_set {
+                return;
+            }
+            schedule();
+        }
+    }
+}
+
+unsafe impl<E: EventFlag> Sync for EventGroup<E> {}
@@ -6,7 +6,7 @@ use core::cell::RefCell;
 use crate::system::semaphore::Semaphore;
 use crate::system::scheduler::BooleanVector;
 use crate::utils::arch::critical_section;
-use crate::tasks::get_curr_tid;
+use crate::kernel::tasks::{get_curr_tid, schedule};
 
 #[cfg(feature = "system_logger")]
 use {
@@ -41,13 +41,14 @@ impl<T: Sized + Clone> Message<T> {
             if let Some(msg) = msg {
                 self.value.replace(msg);
             }
-            self.semaphore.signal_and_release(self.receivers);
+            self.semaphore.signal(self.receivers);
             #[cfg(feature = "system_logger")] {
                 if logging::get_message_broadcast() {
                     logging::report(LogEventType::MessageBroadcast(self.receivers));
                 }
             }
-        })
+        });
+        schedule();
     }
 
     /// Get a copy of the messsage on recieving a message
@@ -1,9 +1,21 @@
 //! Kernel Data-structures. `Private`
 
+#[cfg(feature = "resources")]
 pub mod resource;
+#[cfg(feature = "resources")]
+pub mod rw_resource;
+#[cfg(feature = "resources")]
+pub mod mutex;
 pub mod message;
 pub mod semaphore;
+pub mod barrier;
+pub mod counting_semaphore;
+pub mod binary_semaphore;
+pub mod event_group;
+pub mod task_local;
 pub mod scheduler;
+pub mod ready_queue;
+#[cfg(feature = "resources")]
 mod pi_stack;
 
 #[cfg(any(feature = "events_32", feature = "events_16", feature = "events_64"))]
@@ -13,4 +25,13 @@ pub mod event;
 pub mod system_logger;
 
 #[cfg(feature="task_monitor")]
-pub mod task_monitor;
\ No newline at end of file
+pub mod task_monitor;
+
+#[cfg(feature="deferred_work")]
+pub mod deferred_work;
+
+#[cfg(feature="diag")]
+pub mod diag;
+
+#[cfg(feature="deterministic")]
+pub mod decision_trace;
@@ -0,0 +1,78 @@
+//! # Mutex
+//!
+//! A resource which remembers the task holding it and rejects an unlock by any other task.
+use core::marker::PhantomData;
+use core::ops::Deref;
+
+use crate::KernelError;
+use crate::kernel::tasks::get_curr_tid;
+use crate::system::resource::Resource;
+use crate::system::scheduler::{BooleanVector, TaskId};
+
+/// Guards `T` like a `Resource`, with the unlock checked against the owner.
+pub struct Mutex<T: Sized> {
+    resource: Resource<T>,
+}
+
+impl<T: Sized> Mutex<T> {
+    /// Creates a mutex which the tasks in `tasks_mask` can lock.
+    pub const fn new(val: T, tasks_mask: BooleanVector) -> Self {
+        Self { resource: Resource::new(val, tasks_mask) }
+    }
+
+    /// Locks the mutex for the running task, blocking the competing tasks by the ceiling protocol.
+    /// The mutex is unlocked when the returned guard is dropped.
+    pub fn lock(&self) -> Result<MutexGuard<'_, T>, KernelError> {
+        let inner = self.resource.lock()?;
+        Ok(MutexGuard { mutex: self, inner, _not_send: PhantomData })
+    }
+
+    /// Unlocks the mutex. Returns `KernelError::NotOwner` if the running task doesn't hold it.
+    fn unlock(&self) -> Result<(), KernelError> {
+        if !self.is_locked_by(get_curr_tid()) {
+            return Err(KernelError::NotOwner);
+        }
+        self.resource.unlock()
+    }
+
+    /// Returns true if the task `tid` holds the mutex.
+    pub fn is_locked_by(&self, tid: TaskId) -> bool {
+        self.resource.owner() == Some(tid)
+    }
+
+    /// Runs `handler` with the mutex locked and unlocks it afterwards.
+    pub fn acquire<F,R>(&self, handler: F) -> Result<R,KernelError>
+    where
+        F: Fn(&T) -> R,
+    {
+        self.resource.acquire(handler)
+    }
+}
+
+unsafe impl<T> Sync for Mutex<T> {}
+
+/// Access to a locked mutex, returned by `Mutex::lock`. The mutex is unlocked when the guard is
+/// dropped, hence the data can't be reached once it is unlocked. The guard can't be sent to another
+/// task, as only the owner may unlock the mutex.
+pub struct MutexGuard<'a, T: Sized> {
+    mutex: &'a Mutex<T>,
+    inner: &'a T,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<'a, T: Sized> Deref for MutexGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.inner
+    }
+}
+
+impl<'a, T: Sized> Drop for MutexGuard<'a, T> {
+    /// Unlocks the mutex. Guards must be dropped in the reverse order of locking, an error can't be
+    /// returned from here.
+    fn drop(&mut self) {
+        let res = self.mutex.unlock();
+        debug_assert!(res.is_ok(), "mutex guards dropped out of order");
+    }
+}
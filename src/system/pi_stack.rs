@@ -5,7 +5,7 @@
 
 use crate::config::MAX_RESOURCES;
 use crate::KernelError;
-use crate::system::scheduler::TaskId;
+use crate::system::scheduler::{TaskId, InvariantViolation};
 
 const PI: i32 = -1;
 
@@ -14,6 +14,8 @@ pub struct PiStack {
     top: usize,
     /// This stack is used for locking and unlocking of resources.
     pi_stack: [i32; MAX_RESOURCES],
+    /// Holds the TaskId of the task which pushed the corresponding entry of `pi_stack`.
+    owners: [TaskId; MAX_RESOURCES],
     /// Hold the ceiling of the resource with the highest ceiling amongst the currently locked resources.
     pub system_ceiling: i32,
 }
@@ -23,6 +25,7 @@ impl PiStack {
         Self {
             top: 0,
             pi_stack: [PI; MAX_RESOURCES],
+            owners: [0; MAX_RESOURCES],
             system_ceiling: PI,
         }
     }
@@ -37,14 +40,69 @@ impl PiStack {
         Ok(())
     }
 
-    /// Pushes the passed ceiling onto the pi_stack.
-    pub fn push_stack(&mut self, ceiling: TaskId) -> Result<(),KernelError> {
-        self.top += 1;
-        if self.top >= MAX_RESOURCES {
+    /// Pushes the passed ceiling onto the pi_stack on behalf of `owner`.
+    pub fn push_stack(&mut self, ceiling: TaskId, owner: TaskId) -> Result<(),KernelError> {
+        if self.top + 1 >= MAX_RESOURCES {
             return Err(KernelError::LimitExceeded)
         }
+        self.top += 1;
         self.pi_stack[self.top] = ceiling as i32;
+        self.owners[self.top] = owner;
         self.system_ceiling = ceiling as i32;
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Returns the TaskId of the task which pushed the stack top, `None` if the stack is empty.
+    pub fn top_owner(&self) -> Option<TaskId> {
+        if self.top == 0 {
+            return None
+        }
+        Some(self.owners[self.top])
+    }
+
+    /// Iterates over the locked ceilings from the bottom to the top of the stack as `(depth, ceiling)`.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, TaskId)> + '_ {
+        self.pi_stack[1..=self.top]
+            .iter()
+            .enumerate()
+            .map(|(depth, ceiling)| (depth, *ceiling as TaskId))
+    }
+
+    /// Iterates over the locked ceilings from the bottom to the top of the stack as `(ceiling, owner)`.
+    pub fn entries(&self) -> impl Iterator<Item = (TaskId, TaskId)> + '_ {
+        self.pi_stack[1..=self.top]
+            .iter()
+            .zip(self.owners[1..=self.top].iter())
+            .map(|(ceiling, owner)| (*ceiling as TaskId, *owner))
+    }
+
+    /// Returns true if `tid` pushed any of the ceilings currently on the stack.
+    pub fn is_held_by(&self, tid: TaskId) -> bool {
+        self.owners[1..=self.top].iter().any(|owner| *owner == tid)
+    }
+
+    /// Verifies that the system ceiling is the stack top and that the ceilings increase towards the top.
+    pub fn check_invariants(&self) -> Result<(), InvariantViolation> {
+        if self.system_ceiling != self.pi_stack[self.top] {
+            return Err(InvariantViolation::CeilingMismatch);
+        }
+        for pos in 1..=self.top {
+            if self.pi_stack[pos] <= self.pi_stack[pos - 1] {
+                return Err(InvariantViolation::CeilingMismatch);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the owner of the most recent lock whose ceiling holds back `tid`.
+    pub fn blocker_of(&self, tid: TaskId) -> Option<TaskId> {
+        let mut pos = self.top;
+        while pos > 0 {
+            if self.pi_stack[pos] >= tid as i32 && self.owners[pos] != tid {
+                return Some(self.owners[pos]);
+            }
+            pos -= 1;
+        }
+        None
+    }
+}
@@ -0,0 +1,82 @@
+//! # Ready Queue
+//!
+//! Representations of the set of ready tasks, from which the scheduler picks the highest priority one.
+//! The scheduler keeps one queue, updated whenever a task gets ready or stops being ready.
+//! The bitvector queue is the default, the array queue is selected by the `array_ready_queue` feature.
+#[cfg(feature = "array_ready_queue")]
+use crate::config::MAX_TASKS;
+#[cfg(not(feature = "array_ready_queue"))]
+use crate::system::scheduler::BooleanVector;
+#[cfg(not(feature = "array_ready_queue"))]
+use crate::utils::arch::get_msb;
+
+/// A set of ready tasks ordered by priority.
+pub trait ReadyQueue {
+    /// Returns an empty queue.
+    fn new() -> Self;
+    /// Adds the task of priority `tid` to the queue.
+    fn insert(&mut self, tid: usize);
+    /// Removes the task of priority `tid` from the queue.
+    fn remove(&mut self, tid: usize);
+    /// Returns the highest priority in the queue, `None` if it is empty.
+    fn peek_highest(&self) -> Option<usize>;
+}
+
+/// A ready queue in a single word, the highest priority is found with one CLZ instruction.
+#[cfg(not(feature = "array_ready_queue"))]
+#[derive(Clone, Copy)]
+pub struct BitVectorQueue(BooleanVector);
+
+#[cfg(not(feature = "array_ready_queue"))]
+impl BitVectorQueue {
+    /// Returns an empty queue, usable in constants.
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+}
+
+#[cfg(not(feature = "array_ready_queue"))]
+impl ReadyQueue for BitVectorQueue {
+    fn new() -> Self {
+        Self(0)
+    }
+    fn insert(&mut self, tid: usize) {
+        self.0 |= 1 << tid;
+    }
+    fn remove(&mut self, tid: usize) {
+        self.0 &= !(1 << tid);
+    }
+    fn peek_highest(&self) -> Option<usize> {
+        get_msb(self.0)
+    }
+}
+
+/// A ready queue with a bucket per priority counting the ready tasks of that priority, the highest
+/// priority is found by scanning the buckets downwards.
+#[cfg(feature = "array_ready_queue")]
+#[derive(Clone, Copy)]
+pub struct ArrayQueue([u8; MAX_TASKS]);
+
+#[cfg(feature = "array_ready_queue")]
+impl ArrayQueue {
+    /// Returns an empty queue, usable in constants.
+    pub const fn empty() -> Self {
+        Self([0; MAX_TASKS])
+    }
+}
+
+#[cfg(feature = "array_ready_queue")]
+impl ReadyQueue for ArrayQueue {
+    fn new() -> Self {
+        Self([0; MAX_TASKS])
+    }
+    fn insert(&mut self, tid: usize) {
+        self.0[tid] = self.0[tid].saturating_add(1);
+    }
+    fn remove(&mut self, tid: usize) {
+        self.0[tid] = self.0[tid].saturating_sub(1);
+    }
+    fn peek_highest(&self) -> Option<usize> {
+        self.0.iter().rposition(|&count| count > 0)
+    }
+}
@@ -2,13 +2,22 @@
 //!
 //! Defines the Kernel routines and primitives for resource management.
 use core::cell::{RefCell};
+use core::marker::PhantomData;
+use core::ops::Deref;
 
-use crate::utils::arch::{Mutex, critical_section};
+use crate::utils::arch::{Mutex, critical_section, system_reset, mask_irq, unmask_irq};
+use crate::config::{DeadlockAction, DEADLOCK_ACTION, MAX_RESOURCES, MAX_TASKS};
 use crate::utils::helpers::get_msb_const;
 use crate::system::pi_stack::PiStack;
 use crate::KernelError;
-use crate::kernel::tasks::{block_tasks, get_curr_tid, schedule, unblock_tasks};
-use crate::system::scheduler::{TaskId, BooleanVector};
+use crate::priv_execute;
+use crate::utils::arch::is_privileged;
+use crate::kernel::tasks::{block_tasks_including_self, get_curr_tid, schedule, wake_tasks};
+#[cfg(feature = "ocpp")]
+use crate::kernel::tasks::unblock_tasks;
+#[cfg(feature = "timer")]
+use crate::kernel::timer::{set_timeout, clear_timeout, take_timed_out};
+use crate::system::scheduler::{TaskId, BooleanVector, BlockReason, InvariantViolation};
 
 #[cfg(feature = "system_logger")]
 use {
@@ -19,6 +28,260 @@ use {
 /// Global instance of Resource manager
 static PiStackGlobal: Mutex<RefCell<PiStack>> = Mutex::new(RefCell::new(PiStack::new()));
 
+/// The tasks blocked until a resource is unlocked, in `lock_timeout`.
+static LockWaiters: Mutex<RefCell<BooleanVector>> = Mutex::new(RefCell::new(0));
+
+/// The tasks blocked while a lower priority task holding a resource inherits the priority of a
+/// task waiting for it, with the original ceiling protocol.
+#[cfg(feature = "ocpp")]
+static InheritanceBlocked: Mutex<RefCell<BooleanVector>> = Mutex::new(RefCell::new(0));
+
+/// For each task, the priorities at which it could still lock every resource it has locked so far,
+/// that is the intersection of their task masks. `set_task_priority` only moves a task within it,
+/// so that the ceilings of the resources it uses keep covering it.
+static UsablePriorities: Mutex<RefCell<[BooleanVector; MAX_TASKS]>> = Mutex::new(RefCell::new([!0; MAX_TASKS]));
+
+/// Records that `tid` uses a resource accessible to the tasks of `tasks_mask`.
+pub(crate) fn note_usable(tid: TaskId, tasks_mask: BooleanVector) {
+    critical_section(|cs_token| {
+        UsablePriorities.borrow(cs_token).borrow_mut()[tid as usize] &= tasks_mask;
+    })
+}
+
+/// Returns the priorities at which `tid` can lock every resource it has locked so far, all of them
+/// if it hasn't locked any.
+pub fn usable_priorities(tid: TaskId) -> BooleanVector {
+    critical_section(|cs_token| {
+        UsablePriorities.borrow(cs_token).borrow().get(tid as usize).copied().unwrap_or(!0)
+    })
+}
+
+/// Moves the resources used by `old_tid` to `new_tid`, called when the task changes priority or is
+/// deleted, in which case `new_tid` is `None` and the slot is reset.
+pub(crate) fn move_usable(old_tid: TaskId, new_tid: Option<TaskId>) {
+    critical_section(|cs_token| {
+        let usable = &mut UsablePriorities.borrow(cs_token).borrow_mut();
+        let old = core::mem::replace(&mut usable[old_tid as usize], !0);
+        if let Some(new_tid) = new_tid {
+            usable[new_tid as usize] = old;
+        }
+    })
+}
+
+/// Returns the `Pi_mask`, which is just a boolean vector with all bits up to ceiling (including) set to 1.
+fn get_pi_mask(ceiling: TaskId) -> u32 {
+    let mask;
+    if ceiling < 31 {
+            mask = (1 << (ceiling + 1)) - 1;
+    } else {
+        mask = 0xffffffff
+    }
+    mask
+}
+
+/// The estimated longest time a resource is held for, registered with `Resource::register_max_hold_ticks`.
+#[derive(Clone, Copy)]
+struct HoldTime {
+    /// Address of the resource, identifies the entry.
+    resource: usize,
+    ceiling: TaskId,
+    tasks_mask: BooleanVector,
+    ticks: u32,
+}
+
+/// The registered hold times of the resources.
+static HoldTimes: Mutex<RefCell<[Option<HoldTime>; MAX_RESOURCES]>> = Mutex::new(RefCell::new([None; MAX_RESOURCES]));
+
+/// Returns the worst case number of ticks a task of `priority` can be blocked by the ceiling protocol,
+/// computed from the registered hold times. Under the protocol a task is blocked at most once, for the
+/// longest critical section of a lower priority task on a resource whose ceiling is at least `priority`.
+/// Resources without a registered hold time are not accounted for, nor are priorities beyond `MAX_TASKS`,
+/// which no task can have.
+pub fn worst_case_blocking(priority: TaskId) -> u32 {
+    if priority as usize >= MAX_TASKS {
+        return 0;
+    }
+    // Every resource grants access to the idle task, which never locks one.
+    let lower_priority_tasks = get_pi_mask(priority) & !(1 << priority) & !1;
+    critical_section(|cs_token| {
+        HoldTimes.borrow(cs_token).borrow().iter()
+            .filter_map(|entry| *entry)
+            .filter(|entry| entry.ceiling >= priority && entry.tasks_mask & lower_priority_tasks != 0)
+            .map(|entry| entry.ticks)
+            .max()
+            .unwrap_or(0)
+    })
+}
+
+/// Takes the configured `DEADLOCK_ACTION` on a detected lock order violation; the error is
+/// returned only if the action is `DeadlockAction::ReturnError`.
+fn on_deadlock() -> KernelError {
+    match DEADLOCK_ACTION {
+        DeadlockAction::ReturnError => KernelError::LockOrderViolation,
+        DeadlockAction::Panic => panic!("lock order violation"),
+        DeadlockAction::Reset => system_reset(),
+    }
+}
+
+/// Returns the tasks currently held back by the system ceiling. The owner of the most recent lock
+/// is not part of it, as it is the task which will eventually lower the ceiling, nor is the idle
+/// task, which is never blocked.
+#[cfg(not(feature = "ocpp"))]
+pub fn ceiling_blocked_tasks() -> BooleanVector {
+    critical_section(|cs_token| {
+        let pi_stack = PiStackGlobal.borrow(cs_token).borrow();
+        match pi_stack.top_owner() {
+            Some(owner) => get_pi_mask(pi_stack.system_ceiling as TaskId) & !(1 << owner) & !1,
+            None => 0,
+        }
+    })
+}
+
+/// Returns the tasks currently held back by the system ceiling. With the original ceiling protocol
+/// no task is held back before it tries to lock a resource.
+#[cfg(feature = "ocpp")]
+pub fn ceiling_blocked_tasks() -> BooleanVector {
+    0
+}
+
+/// Returns the ceiling of the most recently locked resource, `None` if no resource is locked.
+pub fn system_ceiling() -> Option<TaskId> {
+    critical_section(|cs_token| {
+        let system_ceiling = PiStackGlobal.borrow(cs_token).borrow().system_ceiling;
+        if system_ceiling < 0 { None } else { Some(system_ceiling as TaskId) }
+    })
+}
+
+/// Verifies the consistency of the ceiling stack.
+pub fn check_invariants() -> Result<(), InvariantViolation> {
+    critical_section(|cs_token| {
+        PiStackGlobal.borrow(cs_token).borrow().check_invariants()
+    })
+}
+
+/// Returns true if `tid` currently holds a resource. The ceilings on the stack were derived from the
+/// priorities of the resources' tasks, hence changing the priority of a task holding a resource
+/// would leave a stale system ceiling; such a change must be rejected with `KernelError::HeldResource`.
+pub fn holds_resource(tid: TaskId) -> bool {
+    critical_section(|cs_token| {
+        PiStackGlobal.borrow(cs_token).borrow().is_held_by(tid)
+    })
+}
+
+/// Removes `tid` from the tasks waiting for a resource to be unlocked, called when the task is deleted.
+pub(crate) fn forget_waiter(tid: TaskId) {
+    critical_section(|cs_token| {
+        *LockWaiters.borrow(cs_token).borrow_mut() &= !(1 << tid);
+        #[cfg(feature = "ocpp")] {
+            *InheritanceBlocked.borrow(cs_token).borrow_mut() &= !(1 << tid);
+        }
+    })
+}
+
+/// Fills `table` with the locked resources from the bottom to the top of the ceiling stack as
+/// `(ceiling, owner)` and returns the number of entries written.
+pub fn resource_table(table: &mut [(TaskId, TaskId)]) -> usize {
+    critical_section(|cs_token| {
+        let pi_stack = PiStackGlobal.borrow(cs_token).borrow();
+        let mut count = 0;
+        for (entry, slot) in pi_stack.entries().zip(table.iter_mut()) {
+            *slot = entry;
+            count += 1;
+        }
+        count
+    })
+}
+
+/// Returns the task holding the resource which keeps `tid` blocked, `None` if no locked resource blocks it.
+pub fn who_blocks(tid: TaskId) -> Option<TaskId> {
+    critical_section(|cs_token| {
+        PiStackGlobal.borrow(cs_token).borrow().blocker_of(tid)
+    })
+}
+
+/// Logs every level of the ceiling stack from the bottom to the top. The nested locks held
+/// when the system hangs help in a deadlock post-mortem.
+#[cfg(feature = "system_logger")]
+pub fn dump_ceiling_stack() {
+    critical_section(|cs_token| {
+        for (depth, ceiling) in PiStackGlobal.borrow(cs_token).borrow().iter() {
+            logging::report(LogEventType::CeilingStack(depth, ceiling));
+        }
+    })
+}
+
+/// Pushes `ceiling` onto the ceiling stack on behalf of `tid` and blocks the tasks up to the ceiling.
+/// Returns `KernelError::AccessDenied` if `ceiling` isn't above the system ceiling, as the resource
+/// might already be held.
+/// With the `deadlock_check` feature, a task nesting a lock whose ceiling isn't above the one it holds
+/// is a lock order violation instead, and the configured `DEADLOCK_ACTION` is taken.
+pub(crate) fn raise_ceiling(ceiling: TaskId, tid: TaskId) -> Result<(),KernelError> {
+    critical_section(|cs_token| {
+        let pi_stack = &mut PiStackGlobal.borrow(cs_token).borrow_mut();
+        #[cfg(feature = "ocpp")] {
+            if pi_stack.blocker_of(tid).is_some() {
+                return Err(KernelError::AccessDenied);
+            }
+        }
+        // A task nesting a lock of a lower or equal ceiling inside its own lock would wait on itself.
+        #[cfg(feature = "deadlock_check")] {
+            if ceiling as i32 <= pi_stack.system_ceiling && pi_stack.top_owner() == Some(tid) {
+                return Err(on_deadlock());
+            }
+        }
+        if ceiling as i32 <= pi_stack.system_ceiling {
+            return Err(KernelError::AccessDenied);
+        }
+        pi_stack.push_stack(ceiling, tid)?;
+        // The idle task is never blocked, so that there is always a task to schedule.
+        // The running task is blocked too when locking on behalf of another task.
+        #[cfg(not(feature = "ocpp"))]
+        block_tasks_including_self(get_pi_mask(ceiling) & !(1 << tid) & !1, BlockReason::Ceiling);
+        Ok(())
+    })
+}
+
+/// Pops `ceiling` off the ceiling stack and unblocks the tasks blocked by it, the caller must call
+/// `schedule` afterwards. Ceilings must be popped in the reverse order of pushing, if `ceiling` is
+/// not the current system ceiling then the configured `DEADLOCK_ACTION` is taken.
+pub(crate) fn lower_ceiling(ceiling: TaskId) -> Result<(),KernelError> {
+    critical_section(|cs_token| {
+        pop_ceiling(ceiling)?;
+        // With nested locks the tasks below the ceiling of an outer lock stay blocked until it is
+        // unlocked too, `wake_tasks` leaves out the tasks still held back by the new system ceiling.
+        #[cfg(not(feature = "ocpp"))]
+        wake_tasks(get_pi_mask(ceiling));
+        #[cfg(feature = "ocpp")]
+        unblock_tasks(InheritanceBlocked.borrow(cs_token).replace(0));
+        // The tasks waiting for a lock retry it. `wake_tasks` reads the ceiling stack, hence
+        // it must not be borrowed here.
+        wake_tasks(LockWaiters.borrow(cs_token).replace(0));
+        Ok(())
+    })
+}
+
+/// Returns true if `tid` holds the most recent lock and `ceiling` isn't above the system ceiling,
+/// hence `tid` could lock a resource of `ceiling` only after unlocking its own.
+#[cfg(all(feature = "timer", not(feature = "deadlock_check")))]
+fn is_below_own_ceiling(ceiling: TaskId, tid: TaskId) -> bool {
+    critical_section(|cs_token| {
+        let pi_stack = PiStackGlobal.borrow(cs_token).borrow();
+        pi_stack.top_owner() == Some(tid) && ceiling as i32 <= pi_stack.system_ceiling
+    })
+}
+
+/// Pops `ceiling` off the ceiling stack. The ceiling stack is borrowed only for the duration of the
+/// call, so that the caller can wake the waiting tasks afterwards, which reads it again.
+fn pop_ceiling(ceiling: TaskId) -> Result<(),KernelError> {
+    critical_section(|cs_token| {
+        let pi_stack = &mut PiStackGlobal.borrow(cs_token).borrow_mut();
+        if ceiling as i32 != pi_stack.system_ceiling {
+            return Err(on_deadlock());
+        }
+        pi_stack.pop_stack()
+    })
+}
+
 /// A Safe Container to store a resource, it can hold resource of any Generic Type
 /// and allow safe access to it without ending up in Data races or Deadlocks.
 #[derive(Debug)]
@@ -30,6 +293,33 @@ pub struct Resource<T: Sized>
     tasks_mask: BooleanVector,
     /// This field holds the actual resource that has to be locked.
     inner: T,
+    /// The task currently holding the resource.
+    owner: RefCell<Option<TaskId>>,
+    /// Number of ticks `acquire` waits for the resource before failing with `KernelError::Timeout`,
+    /// `None` if it fails right away when the resource can't be locked.
+    #[cfg(feature = "timer")]
+    default_timeout: Option<u32>,
+    /// The interrupt disabled while the resource is locked.
+    irq: Option<u8>,
+    /// Number of times the owner locked the resource again without unlocking it.
+    #[cfg(feature = "recursive_resources")]
+    depth: RefCell<u32>,
+    /// Number of times the resource was locked.
+    #[cfg(feature = "resource_stats")]
+    locks: RefCell<u32>,
+    /// Number of lock calls which blocked other tasks.
+    #[cfg(feature = "resource_stats")]
+    blocks: RefCell<u32>,
+}
+
+/// Lock and contention counters of a resource, returned by `Resource::stats`.
+#[cfg(feature = "resource_stats")]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ResourceStats {
+    /// Number of times the resource was locked.
+    pub locks: u32,
+    /// Number of lock calls which blocked other tasks.
+    pub blocks: u32,
 }
 
 impl<T: Sized> Resource<T> {
@@ -41,74 +331,394 @@ impl<T: Sized> Resource<T> {
             inner: val,
             tasks_mask: tasks_mask,
             ceiling: get_msb_const(tasks_mask) as TaskId,
+            owner: RefCell::new(None),
+            #[cfg(feature = "timer")]
+            default_timeout: None,
+            irq: None,
+            #[cfg(feature = "recursive_resources")]
+            depth: RefCell::new(0),
+            #[cfg(feature = "resource_stats")]
+            locks: RefCell::new(0),
+            #[cfg(feature = "resource_stats")]
+            blocks: RefCell::new(0),
         }
     }
 
-    /// Returns the `Pi_mask`, which is just a boolean vector with all bits up to ceiling (including) set to 1.
-    fn get_pi_mask(ceiling: TaskId) -> u32 {
-        let mask;
-        if ceiling < 31 {
-                mask = (1 << (ceiling + 1)) - 1;
-        } else {
-            mask = 0xffffffff
-        }
-        mask
+    /// Creates a Resource shared with the handler of the interrupt `irq`. The interrupt is disabled in
+    /// the NVIC while a task holds the resource, so that the handler can't access it concurrently.
+    /// The handler itself must not lock the resource.
+    pub const fn new_with_irq(val: T, tasks_mask: BooleanVector, irq: u8) -> Self {
+        let mut resource = Self::new(val, tasks_mask);
+        resource.irq = Some(irq);
+        resource
     }
-    
+
+    /// Sets the number of ticks `acquire` waits for the resource to be lockable before failing with
+    /// `KernelError::Timeout`.
+    ///
+    /// ## Example
+    /// ```rust
+    /// static res: Resource<u32> = Resource::new(0, 0b110).with_default_timeout(10);
+    /// ```
+    #[cfg(feature = "timer")]
+    pub const fn with_default_timeout(mut self, ticks: u32) -> Self {
+        self.default_timeout = Some(ticks);
+        self
+    }
+
+    /// Returns the number of times the resource was locked and the number of lock calls which blocked
+    /// other tasks, by raising the ceiling or, with `ocpp`, through priority inheritance.
+    #[cfg(feature = "resource_stats")]
+    pub fn stats(&self) -> ResourceStats {
+        critical_section(|_| ResourceStats {
+            locks: *self.locks.borrow(),
+            blocks: *self.blocks.borrow(),
+        })
+    }
+
+    /// Returns the TaskId of the task currently holding the resource.
+    pub fn owner(&self) -> Option<TaskId> {
+        critical_section(|_| *self.owner.borrow())
+    }
+
     /// Lock the Resource for the currently running task and blocks the competing tasks 
-    fn lock(&self) -> Result<&T,KernelError> {
-        critical_section(|cs_token| {
-            let pi_stack = &mut PiStackGlobal.borrow(cs_token).borrow_mut();
-            let curr_tid = get_curr_tid() as u32;
-            
-            let ceiling = self.ceiling;
+    #[cfg(not(feature = "ocpp"))]
+    pub(crate) fn lock(&self) -> Result<&T,KernelError> {
+        self.lock_for(get_curr_tid() as TaskId)
+    }
+
+    /// Lock the Resource for the currently running task with the original ceiling protocol: the task is
+    /// blocked only if a resource held by another task has a ceiling at or above its priority. The
+    /// holder then inherits the priority of the task, by blocking the tasks of priority in between.
+    #[cfg(feature = "ocpp")]
+    pub(crate) fn lock(&self) -> Result<&T,KernelError> {
+        let curr_tid = get_curr_tid() as TaskId;
+        loop {
+            let res = critical_section(|cs_token| {
+                let blocker = PiStackGlobal.borrow(cs_token).borrow().blocker_of(curr_tid);
+                match blocker {
+                    Some(owner) => {
+                        let mask = (get_pi_mask(curr_tid) & !get_pi_mask(owner)) | (1 << curr_tid);
+                        *InheritanceBlocked.borrow(cs_token).borrow_mut() |= mask;
+                        #[cfg(feature = "resource_stats")] {
+                            *self.blocks.borrow_mut() += 1;
+                        }
+                        *LockWaiters.borrow(cs_token).borrow_mut() |= 1 << curr_tid;
+                        block_tasks_including_self(mask, BlockReason::Resource);
+                        None
+                    }
+                    None => Some(self.lock_for(curr_tid)),
+                }
+            });
+            match res {
+                Some(res) => return res,
+                None => schedule(),
+            }
+        }
+    }
+
+    /// Lock the Resource on behalf of `curr_tid` and blocks the competing tasks.
+    fn lock_for(&self, curr_tid: TaskId) -> Result<&T,KernelError> {
+        critical_section(|_| {
             let pid_mask = 1 << curr_tid;
             if self.tasks_mask & pid_mask != pid_mask {
                 return Err(KernelError::AccessDenied);
             }
-            if ceiling as i32 > pi_stack.system_ceiling {
-                pi_stack.push_stack(ceiling)?;
-                let mask = Self::get_pi_mask(ceiling) & !(1 << curr_tid);
-                block_tasks(mask);
-                #[cfg(feature = "system_logger")] {
-                    if logging::get_resource_lock() {
-                        logging::report(LogEventType::ResourceLock(curr_tid));
+            note_usable(curr_tid, self.tasks_mask);
+            // The owner locking the resource again only counts the nesting, the ceiling is already raised.
+            #[cfg(feature = "recursive_resources")] {
+                if *self.owner.borrow() == Some(curr_tid) {
+                    *self.depth.borrow_mut() += 1;
+                    return Ok(&self.inner);
+                }
+            }
+            raise_ceiling(self.ceiling, curr_tid)?;
+            #[cfg(feature = "resource_stats")] {
+                *self.locks.borrow_mut() += 1;
+                #[cfg(not(feature = "ocpp"))] {
+                    if get_pi_mask(self.ceiling) & !pid_mask & !1 != 0 {
+                        *self.blocks.borrow_mut() += 1;
                     }
                 }
-                return Ok(&self.inner);
             }
-            return Err(KernelError::AccessDenied);
+            if let Some(irq) = self.irq {
+                mask_irq(irq);
+            }
+            self.owner.replace(Some(curr_tid));
+            #[cfg(feature = "system_logger")] {
+                if logging::get_resource_lock() {
+                    logging::report(LogEventType::ResourceLock(curr_tid));
+                }
+            }
+            Ok(&self.inner)
         })
     }
 
-    /// Unlocks the Resource and unblocks the tasks which were blocked during the call to lock
-    fn unlock(&self) -> Result<(),KernelError> {
-        critical_section(|cs_token| {
-            let pi_stack = &mut PiStackGlobal.borrow(cs_token).borrow_mut();
-            if self.ceiling as i32 == pi_stack.system_ceiling {
-                pi_stack.pop_stack()?;
-                let mask = Self::get_pi_mask(self.ceiling);
-                unblock_tasks(mask);
-                schedule();
+    /// Unlocks the Resource and unblocks the tasks which were blocked during the call to lock.
+    /// Resources must be unlocked in the reverse order of locking, if the resource's ceiling is
+    /// not the current system ceiling then the configured `DEADLOCK_ACTION` is taken.
+    pub(crate) fn unlock(&self) -> Result<(),KernelError> {
+        #[cfg(feature = "recursive_resources")] {
+            let is_nested = critical_section(|_| {
+                let depth = &mut self.depth.borrow_mut();
+                if **depth > 0 {
+                    **depth -= 1;
+                    return true;
+                }
+                false
+            });
+            if is_nested {
+                return Ok(());
+            }
+        }
+        critical_section(|_| {
+            lower_ceiling(self.ceiling)?;
+            if let Some(irq) = self.irq {
+                unmask_irq(irq);
             }
+            self.owner.replace(None);
             #[cfg(feature = "system_logger")] {
                 if logging::get_resource_unlock() {
                     logging::report(LogEventType::ResourceUnlock(get_curr_tid() as u32));
                 }
             }
             Ok(())
+        })?;
+        schedule();
+        Ok(())
+    }
+
+    /// Same as `lock_guard`, but if the resource can't be locked the running task is blocked until it
+    /// can be, or until `ticks` ticks pass, in which case `KernelError::Timeout` is returned.
+    ///
+    /// A ceiling is pushed onto the ceiling stack only once the lock succeeds, a waiting task pushes
+    /// nothing. Hence a task that times out leaves the ceiling stack as it found it, it is only
+    /// removed from the tasks woken on the next unlock.
+    #[cfg(feature = "timer")]
+    pub fn lock_timeout(&self, ticks: u32) -> Result<ResourceGuard<'_, T>, KernelError> {
+        let inner = self.lock_waiting(ticks)?;
+        Ok(ResourceGuard { resource: self, inner, _not_send: PhantomData })
+    }
+
+    /// Same as `lock`, but if the resource can't be locked the running task is blocked until it can
+    /// be, or until `ticks` ticks pass, in which case `KernelError::Timeout` is returned.
+    #[cfg(feature = "timer")]
+    fn lock_waiting(&self, ticks: u32) -> Result<&T,KernelError> {
+        let curr_tid = get_curr_tid() as TaskId;
+        if self.tasks_mask & (1 << curr_tid) == 0 {
+            return Err(KernelError::AccessDenied);
+        }
+        // Waiting on a resource held by the task itself would never end.
+        #[cfg(not(feature = "recursive_resources"))] {
+            if self.owner() == Some(curr_tid) {
+                return Err(KernelError::AccessDenied);
+            }
+        }
+        // Nor would waiting on a ceiling raised by the task itself. With `deadlock_check`, `lock_for`
+        // reports it as a lock order violation right away instead.
+        #[cfg(not(feature = "deadlock_check"))] {
+            if self.owner() != Some(curr_tid) && is_below_own_ceiling(self.ceiling, curr_tid) {
+                return Err(KernelError::AccessDenied);
+            }
+        }
+        set_timeout(curr_tid, ticks);
+        loop {
+            let res = critical_section(|cs_token| {
+                match self.lock_for(curr_tid) {
+                    Err(KernelError::AccessDenied) => {
+                        if take_timed_out(curr_tid) {
+                            *LockWaiters.borrow(cs_token).borrow_mut() &= !(1 << curr_tid);
+                            return Some(Err(KernelError::Timeout));
+                        }
+                        *LockWaiters.borrow(cs_token).borrow_mut() |= 1 << curr_tid;
+                        block_tasks_including_self(1 << curr_tid, BlockReason::Resource);
+                        None
+                    }
+                    res => Some(res),
+                }
+            });
+            match res {
+                Some(res) => {
+                    clear_timeout(curr_tid);
+                    return res;
+                }
+                None => schedule(),
+            }
+        }
+    }
+
+    /// Registers the estimated longest time, in ticks, for which any task holds the resource.
+    /// It is used by `worst_case_blocking`, registering again replaces the estimate.
+    pub fn register_max_hold_ticks(&'static self, ticks: u32) -> Result<(),KernelError> {
+        let resource = self as *const Self as usize;
+        critical_section(|cs_token| {
+            let hold_times = &mut HoldTimes.borrow(cs_token).borrow_mut();
+            let pos = hold_times.iter().position(|entry| entry.map_or(false, |entry| entry.resource == resource))
+                .or_else(|| hold_times.iter().position(|entry| entry.is_none()))
+                .ok_or(KernelError::LimitExceeded)?;
+            hold_times[pos] = Some(HoldTime {
+                resource,
+                ceiling: self.ceiling,
+                tasks_mask: self.tasks_mask,
+                ticks,
+            });
+            Ok(())
         })
     }
+
+    /// Locks the resource on behalf of `owner` as if `owner` had called `lock`, so that an initialization
+    /// routine can hand over a resource which no other task may touch until `owner` calls `force_unlock`.
+    /// Can only be called from a privileged context.
+    pub fn force_lock(&self, owner: TaskId) -> Result<(),KernelError> {
+        priv_execute!({
+            self.lock_for(owner)?;
+            schedule();
+            Ok(())
+        })
+    }
+
+    /// Unlocks a resource locked by `force_lock`. Only the owner of the resource may unlock it,
+    /// otherwise `KernelError::AccessDenied` is returned.
+    pub fn force_unlock(&self) -> Result<(),KernelError> {
+        if self.owner() != Some(get_curr_tid() as TaskId) {
+            return Err(KernelError::AccessDenied);
+        }
+        self.unlock()
+    }
+
+    /// Panics in debug builds if the running task isn't `tid`, and returns the resource so that the
+    /// access can be chained, as in `resource.with_task_assertion(2).acquire(...)`. It catches a task
+    /// reaching code meant for another task, before the access check of `lock` does.
+    pub fn with_task_assertion(&self, tid: TaskId) -> &Self {
+        debug_assert_eq!(get_curr_tid(), tid, "resource accessed from an unexpected task");
+        self
+    }
+
     /// A helper function that ensures that if a resource is locked, it is unlocked.
     pub fn acquire<F,R>(&self, handler: F) -> Result<R,KernelError>
     where
         F: Fn(&T) -> R,
     {
-        let value = self.lock()?;
+        let value = self.lock_default()?;
         let res = handler(value);
         self.unlock()?;
         return Ok(res);
     }
+
+    /// Locks the resource and returns a guard which gives access to it and unlocks it when dropped,
+    /// so that the resource can be used across several statements without a closure.
+    pub fn lock_guard(&self) -> Result<ResourceGuard<'_, T>, KernelError> {
+        let inner = self.lock_default()?;
+        Ok(ResourceGuard { resource: self, inner, _not_send: PhantomData })
+    }
+
+    /// Locks the resource if it is free, without blocking. Returns `Ok(None)` if the resource is held,
+    /// so that a polling task can do other work, and `KernelError::AccessDenied` only if the running
+    /// task isn't allowed to access the resource.
+    pub fn try_lock(&self) -> Result<Option<ResourceGuard<'_, T>>, KernelError> {
+        let curr_tid = get_curr_tid() as TaskId;
+        if self.tasks_mask & (1 << curr_tid) == 0 {
+            return Err(KernelError::AccessDenied);
+        }
+        match self.lock_for(curr_tid) {
+            Ok(inner) => Ok(Some(ResourceGuard { resource: self, inner, _not_send: PhantomData })),
+            Err(KernelError::AccessDenied) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Locks the resource, waiting for it up to the default timeout if one is set.
+    fn lock_default(&self) -> Result<&T,KernelError> {
+        #[cfg(feature = "timer")] {
+            if let Some(ticks) = self.default_timeout {
+                return self.lock_waiting(ticks);
+            }
+        }
+        self.lock()
+    }
+}
+
+/// Access to a locked resource, returned by `Resource::lock_guard`. The resource is unlocked, and
+/// the tasks it blocked are rescheduled, when the guard is dropped. The guard can't be sent to
+/// another task, as only the task which locked the resource may unlock it.
+pub struct ResourceGuard<'a, T: Sized> {
+    resource: &'a Resource<T>,
+    inner: &'a T,
+    _not_send: PhantomData<*const ()>,
 }
 
-unsafe impl<T> Sync for Resource<T> {}
\ No newline at end of file
+impl<'a, T: Sized> Deref for ResourceGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.inner
+    }
+}
+
+impl<'a, T: Sized> Drop for ResourceGuard<'a, T> {
+    /// Unlocks the resource. Guards must be dropped in the reverse order of locking, otherwise the
+    /// configured `DEADLOCK_ACTION` is taken, an error can't be returned from here.
+    fn drop(&mut self) {
+        let res = self.resource.unlock();
+        debug_assert!(res.is_ok(), "resource guards dropped out of order");
+    }
+}
+
+impl<T: Copy> Resource<T> {
+    /// Returns a copy of the resource without going through the ceiling protocol. The copy is made
+    /// inside a critical section, hence it is coherent, and neither the blocked tasks nor the
+    /// ceiling stack are changed. The access rights of the calling task are still checked.
+    pub fn get_copy(&self) -> Result<T, KernelError> {
+        critical_section(|_| {
+            let pid_mask = 1 << get_curr_tid();
+            if self.tasks_mask & pid_mask != pid_mask {
+                return Err(KernelError::AccessDenied);
+            }
+            Ok(self.inner)
+        })
+    }
+}
+
+unsafe impl<T> Sync for Resource<T> {}
+
+/// Proof that the code holding it runs as the task of priority `TASK`.
+#[derive(Clone, Copy)]
+pub struct TaskContext<const TASK: usize> {
+    _private: (),
+}
+
+impl<const TASK: usize> TaskContext<TASK> {
+    /// Returns the context if the running task is `TASK`, `None` otherwise. A task takes it once at
+    /// the start of its handler.
+    pub fn new() -> Option<Self> {
+        if get_curr_tid() as usize == TASK {
+            Some(Self { _private: () })
+        } else {
+            None
+        }
+    }
+}
+
+/// A binding of a resource to the task of priority `TASK`. It can only be accessed with a
+/// `TaskContext<TASK>`, hence an access from code holding the context of another task doesn't compile.
+pub struct ResourceFor<T: 'static, const TASK: usize> {
+    resource: &'static Resource<T>,
+}
+
+impl<T: 'static, const TASK: usize> ResourceFor<T, TASK> {
+    /// Binds `resource` to `TASK`, returns `KernelError::AccessDenied` if `TASK` can't access it.
+    pub fn new(resource: &'static Resource<T>) -> Result<Self, KernelError> {
+        if resource.tasks_mask & (1 << TASK) == 0 {
+            return Err(KernelError::AccessDenied);
+        }
+        Ok(Self { resource })
+    }
+
+    /// Same as `Resource::acquire`, for the task the context proves to be running.
+    pub fn acquire<F,R>(&self, _context: &TaskContext<TASK>, handler: F) -> Result<R,KernelError>
+    where
+        F: Fn(&T) -> R,
+    {
+        self.resource.acquire(handler)
+    }
+}
\ No newline at end of file
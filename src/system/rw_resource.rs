@@ -0,0 +1,117 @@
+//! # Read-Write Resource
+//!
+//! A resource shared by many readers and few writers. Readers only raise the system ceiling to the
+//! priority of the highest writer, so readers of higher priority can still preempt and read, while
+//! a writer raises it to the priority of the highest reader or writer.
+use core::cell::RefCell;
+
+use crate::KernelError;
+use crate::kernel::tasks::{get_curr_tid, schedule};
+use crate::system::resource::{lower_ceiling, raise_ceiling, system_ceiling, note_usable};
+use crate::system::scheduler::{BooleanVector, TaskId};
+use crate::utils::arch::critical_section;
+use crate::utils::helpers::get_msb_const;
+
+/// A container allowing any number of concurrent readers or a single writer.
+pub struct RwResource<T: Sized> {
+    /// The ceiling raised by readers, it is the priority of the highest priority writer.
+    read_ceiling: TaskId,
+    /// The ceiling raised by writers, it is the priority of the highest priority reader or writer.
+    write_ceiling: TaskId,
+    readers_mask: BooleanVector,
+    writers_mask: BooleanVector,
+    inner: T,
+    /// Number of tasks currently reading.
+    readers: RefCell<u32>,
+    /// The task currently writing.
+    writer: RefCell<Option<TaskId>>,
+}
+
+impl<T: Sized> RwResource<T> {
+    /// Creates a new resource, tasks in `readers_mask` may `read` it and tasks in `writers_mask` may `write` it.
+    pub const fn new(val: T, readers_mask: BooleanVector, writers_mask: BooleanVector) -> Self {
+        let readers_mask = readers_mask | 1;
+        let writers_mask = writers_mask | 1;
+        Self {
+            read_ceiling: get_msb_const(writers_mask) as TaskId,
+            write_ceiling: get_msb_const(readers_mask | writers_mask) as TaskId,
+            readers_mask,
+            writers_mask,
+            inner: val,
+            readers: RefCell::new(0),
+            writer: RefCell::new(None),
+        }
+    }
+
+    /// Runs `handler` with shared access to the resource. If other tasks are already reading and the
+    /// system ceiling covers the writers, the ceiling is not raised again.
+    pub fn read<F, R>(&self, handler: F) -> Result<R, KernelError>
+    where
+        F: Fn(&T) -> R,
+    {
+        let curr_tid = get_curr_tid() as TaskId;
+        if self.readers_mask & (1 << curr_tid) == 0 {
+            return Err(KernelError::AccessDenied);
+        }
+        note_usable(curr_tid, self.readers_mask);
+        let raised = critical_section(|_| {
+            if self.writer.borrow().is_some() {
+                return Err(KernelError::AccessDenied);
+            }
+            let raised = match system_ceiling() {
+                Some(ceiling) if ceiling >= self.read_ceiling && *self.readers.borrow() > 0 => false,
+                _ => {
+                    raise_ceiling(self.read_ceiling, curr_tid)?;
+                    true
+                }
+            };
+            *self.readers.borrow_mut() += 1;
+            Ok(raised)
+        })?;
+        let res = handler(&self.inner);
+        critical_section(|_| {
+            *self.readers.borrow_mut() -= 1;
+            if raised {
+                lower_ceiling(self.read_ceiling)?;
+            }
+            Ok(())
+        })?;
+        schedule();
+        Ok(res)
+    }
+
+    /// Runs `handler` with exclusive access to the resource. Fails with `KernelError::AccessDenied`
+    /// if the resource is being read or written.
+    pub fn write<F, R>(&self, handler: F) -> Result<R, KernelError>
+    where
+        F: Fn(&T) -> R,
+    {
+        let curr_tid = get_curr_tid() as TaskId;
+        if self.writers_mask & (1 << curr_tid) == 0 {
+            return Err(KernelError::AccessDenied);
+        }
+        note_usable(curr_tid, self.writers_mask);
+        critical_section(|_| {
+            if *self.readers.borrow() > 0 || self.writer.borrow().is_some() {
+                return Err(KernelError::AccessDenied);
+            }
+            raise_ceiling(self.write_ceiling, curr_tid)?;
+            self.writer.replace(Some(curr_tid));
+            Ok(())
+        })?;
+        let res = handler(&self.inner);
+        critical_section(|_| {
+            self.writer.replace(None);
+            lower_ceiling(self.write_ceiling)
+        })?;
+        schedule();
+        Ok(res)
+    }
+
+    /// Returns the number of tasks currently reading the resource.
+    pub fn readers(&self) -> u32 {
+        critical_section(|_| *self.readers.borrow())
+    }
+}
+
+unsafe impl<T> Sync for RwResource<T> {}
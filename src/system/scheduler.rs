@@ -2,15 +2,50 @@
 //! The Definition of Data-structures required for task management.
 //!
 use crate::config::MAX_TASKS;
-use crate::utils::arch::{get_msb, save_context, load_context, wait_for_interrupt};
+#[cfg(feature = "round_robin")]
+use crate::config::MAX_ROUND_ROBIN_BANDS;
+#[cfg(feature = "lazy_stack_paint")]
+use crate::config::{LAZY_PAINT_INITIAL_WORDS, LAZY_PAINT_STEP_WORDS};
+use crate::utils::arch::{save_context, load_context, wait_for_interrupt};
+#[cfg(feature = "round_robin")]
+use crate::utils::arch::get_msb;
+use crate::system::ready_queue::ReadyQueue;
+#[cfg(not(feature = "array_ready_queue"))]
+use crate::system::ready_queue::BitVectorQueue;
+#[cfg(feature = "array_ready_queue")]
+use crate::system::ready_queue::ArrayQueue;
 use crate::KernelError;
+use core::fmt;
 
 #[cfg(feature = "task_monitor")]
 use crate::kernel::task_monitor::{clear_deadline, set_deadline};
+#[cfg(feature = "edf_scheduler")]
+use crate::kernel::timer::get_time;
 
 pub type TaskId = u32;
 pub type BooleanVector = u32;
 
+/// The ready queue representation used to pick the next task.
+#[cfg(not(feature = "array_ready_queue"))]
+type ActiveReadyQueue = BitVectorQueue;
+#[cfg(feature = "array_ready_queue")]
+type ActiveReadyQueue = ArrayQueue;
+
+/// Number of words reserved at the top of every task stack for the saved context
+/// (8 words stacked by the hardware on exception entry and 8 words saved by the kernel).
+/// A task's stack must hold the handler's own requirement plus `CONTEXT_FRAME_WORDS`.
+#[cfg(not(feature = "fpu"))]
+pub const CONTEXT_FRAME_WORDS: usize = 16;
+
+/// Number of words reserved at the top of every task stack for the saved context
+/// (8 words stacked by the hardware on exception entry, 8 core registers and 33 FPU registers saved by the kernel).
+/// A task's stack must hold the handler's own requirement plus `CONTEXT_FRAME_WORDS`.
+#[cfg(feature = "fpu")]
+pub const CONTEXT_FRAME_WORDS: usize = 49;
+
+/// The pattern a task's stack is filled with on creation, words still holding it were never used.
+const STACK_PAINT: u32 = 0xDEADBEEF;
+
 /// Maintains state of all tasks in the Kernel
 #[repr(C)]
 pub struct Scheduler {
@@ -24,9 +59,222 @@ pub struct Scheduler {
     pub blocked_tasks: BooleanVector,
     /// A boolean vector in which, if a bit at a position is true, it implies that the task is blocked and cannot be scheduled even if it’s active.
     pub active_tasks: BooleanVector,
+    /// A boolean vector of the tasks suspended by `suspend_group`, they aren't scheduled until resumed.
+    pub suspended_tasks: BooleanVector,
+    /// The ready tasks, kept in step with the three masks above by the methods changing them.
+    ready_queue: ActiveReadyQueue,
     /// A variable which decided if the scheduler should preemptively schedule tasks or not.
     pub is_preemptive: bool,
     pub preempt_disable_count: u32,
+    /// Set by `request_shutdown`, the next context switch returns to `try_start_kernel` instead.
+    pub shutdown: Option<ShutdownReason>,
+    /// The number of tasks loaded by PendSV since boot, it wraps around.
+    pub context_switches: u32,
+    /// The reason given by the routine which requested the pending context switch, if any.
+    pub switch_hint: Option<SwitchReason>,
+    /// The reason of the most recent context switch.
+    pub last_switch_reason: Option<SwitchReason>,
+    /// The priorities set aside by `reserve_priorities` for tasks created by `spawn_dynamic`.
+    pub reserved_priorities: BooleanVector,
+    /// The priority bands whose ready tasks share the CPU in turns, set by `enable_round_robin`.
+    #[cfg(feature = "round_robin")]
+    pub round_robin_bands: [Option<RoundRobinBand>; MAX_ROUND_ROBIN_BANDS],
+    /// The absolute deadline of each task's current release, for tasks created with a non-zero deadline.
+    #[cfg(feature = "edf_scheduler")]
+    pub absolute_deadlines: [Option<u32>; MAX_TASKS],
+}
+
+/// A band of consecutive priorities, the ready tasks in it are treated as having the same priority
+/// and run in turns of `quantum` ticks, starting from the highest priority.
+#[cfg(feature = "round_robin")]
+#[derive(Clone, Copy)]
+pub struct RoundRobinBand {
+    /// The priorities in the band.
+    pub tasks: BooleanVector,
+    /// Number of ticks a task of the band runs before the next ready one gets its turn.
+    pub quantum: u32,
+    /// Ticks left in the turn of `cursor`.
+    pub ticks_left: u32,
+    /// The task whose turn it is, it needn't be ready.
+    pub cursor: usize,
+}
+
+/// Why the running task was switched out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SwitchReason {
+    /// A higher priority task got ready, by being unblocked or resumed.
+    Preempted,
+    /// The task blocked or was suspended.
+    Blocked,
+    /// The task called `task_exit`.
+    Exited,
+    /// The task called `task_yield`.
+    Yielded,
+    /// A higher priority task was released.
+    Released,
+    /// The task used up its round-robin quantum and another task of its band got the turn.
+    QuantumExpired,
+}
+
+/// The reason `try_start_kernel` returned.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ShutdownReason {
+    /// `request_shutdown` was called with the code.
+    Requested(u32),
+}
+
+/// A copy of the scheduler state, taken in a single critical section.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SchedulerSnapshot {
+    pub curr_tid: TaskId,
+    pub started: bool,
+    pub active_tasks: BooleanVector,
+    pub blocked_tasks: BooleanVector,
+    pub is_preemptive: bool,
+    /// Ceiling of the most recently locked resource, `None` if no resource is locked.
+    pub system_ceiling: Option<TaskId>,
+}
+
+impl SchedulerSnapshot {
+    /// Returns the changes from `self` to `other`.
+    pub fn diff(&self, other: &SchedulerSnapshot) -> SnapshotDiff {
+        fn changed<T: PartialEq + Copy>(from: T, to: T) -> Option<(T, T)> {
+            if from != to { Some((from, to)) } else { None }
+        }
+        SnapshotDiff {
+            curr_tid: changed(self.curr_tid, other.curr_tid),
+            started: changed(self.started, other.started),
+            activated: other.active_tasks & !self.active_tasks,
+            deactivated: self.active_tasks & !other.active_tasks,
+            blocked: other.blocked_tasks & !self.blocked_tasks,
+            unblocked: self.blocked_tasks & !other.blocked_tasks,
+            is_preemptive: changed(self.is_preemptive, other.is_preemptive),
+            system_ceiling: changed(self.system_ceiling, other.system_ceiling),
+        }
+    }
+}
+
+/// The changes between two `SchedulerSnapshot`s, as `(before, after)` for the scalar fields and as the
+/// bits which were set or cleared for the boolean vectors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SnapshotDiff {
+    pub curr_tid: Option<(TaskId, TaskId)>,
+    pub started: Option<(bool, bool)>,
+    pub activated: BooleanVector,
+    pub deactivated: BooleanVector,
+    pub blocked: BooleanVector,
+    pub unblocked: BooleanVector,
+    pub is_preemptive: Option<(bool, bool)>,
+    pub system_ceiling: Option<(Option<TaskId>, Option<TaskId>)>,
+}
+
+impl SnapshotDiff {
+    /// Returns true if the two snapshots were equal.
+    pub fn is_empty(&self) -> bool {
+        self.curr_tid.is_none()
+            && self.started.is_none()
+            && self.activated == 0
+            && self.deactivated == 0
+            && self.blocked == 0
+            && self.unblocked == 0
+            && self.is_preemptive.is_none()
+            && self.system_ceiling.is_none()
+    }
+}
+
+/// Formats the changes as a comma separated list, such as `curr_tid 1 -> 3, activated 0b1000`.
+impl fmt::Display for SnapshotDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no change");
+        }
+        let mut separator = "";
+        if let Some((from, to)) = self.curr_tid {
+            write!(f, "{}curr_tid {} -> {}", separator, from, to)?;
+            separator = ", ";
+        }
+        if let Some((from, to)) = self.started {
+            write!(f, "{}started {} -> {}", separator, from, to)?;
+            separator = ", ";
+        }
+        for (name, mask) in [
+            ("activated", self.activated),
+            ("deactivated", self.deactivated),
+            ("blocked", self.blocked),
+            ("unblocked", self.unblocked),
+        ].iter() {
+            if *mask != 0 {
+                write!(f, "{}{} {:#b}", separator, name, mask)?;
+                separator = ", ";
+            }
+        }
+        if let Some((from, to)) = self.is_preemptive {
+            write!(f, "{}is_preemptive {} -> {}", separator, from, to)?;
+            separator = ", ";
+        }
+        if let Some((from, to)) = self.system_ceiling {
+            write!(f, "{}system_ceiling {:?} -> {:?}", separator, from, to)?;
+        }
+        Ok(())
+    }
+}
+
+/// The scheduling state of a task, returned by `get_task_state`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TaskState {
+    /// The task is on the CPU.
+    Running,
+    /// The task would run if it were the highest priority ready task.
+    Ready,
+    /// The task waits on a kernel primitive.
+    Blocked,
+    /// The task was suspended and won't run until resumed.
+    Suspended,
+    /// The task wasn't released yet, exited, or doesn't exist.
+    Inactive,
+}
+
+/// The configuration a task was created with.
+#[derive(Clone, Copy, Debug)]
+pub struct TaskInfo {
+    pub priority: TaskId,
+    #[cfg(feature="task_monitor")]
+    pub deadline: u32,
+    /// Size of the task's stack in words.
+    pub stack_len: usize,
+    pub name: Option<&'static str>,
+}
+
+/// The cause of a task getting blocked, passed to the block hook.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlockReason {
+    /// Held back by the system ceiling raised by a locked resource.
+    Ceiling,
+    /// Waiting for a resource held by another task.
+    Resource,
+    /// Waiting in `Semaphore::wait`.
+    Semaphore,
+    /// Waiting in `EventGroup::wait`.
+    EventGroup,
+    /// Waiting in `Barrier::wait`.
+    Barrier,
+    /// Blocked directly through `block_tasks`.
+    Other,
+}
+
+/// Inconsistencies in the kernel state, detected by `check_invariants`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InvariantViolation {
+    /// The running task is not active or is blocked.
+    CurrentTaskNotReady,
+    /// The idle task is not active or is blocked.
+    IdleTaskNotReady,
+    /// A task is active but has no task control block.
+    ActiveTaskWithoutTcb,
+    /// The ready queue doesn't hold the highest priority ready task.
+    ReadyQueueMismatch,
+    /// The system ceiling doesn't match the top of the ceiling stack, or the stack isn't increasing.
+    CeilingMismatch,
 }
 
 /// A single tasks's state
@@ -36,6 +284,30 @@ pub struct Scheduler {
 pub struct TaskControlBlock {
     /// Holds a reference to the stack pointer for the task.
     stack_pointer: usize, // current stack pointer of this thread
+    /// The entry point of the task, it is run from the start on `restart_task`.
+    handler: fn() -> !,
+    /// Address of the lowest word of the task's stack.
+    stack_base: usize,
+    /// Number of words in the task's stack.
+    stack_len: usize,
+    /// A human readable name of the task, for diagnostics.
+    name: Option<&'static str>,
+    /// Index of the lowest painted word of the stack, the words below it are not painted yet.
+    painted_from: usize,
+    /// Nesting depth of the task's `defer_scheduling`, `schedule` only records a pending switch
+    /// while it is non-zero. It is kept per task, so that a task blocking in a deferred region
+    /// doesn't defer the scheduling of the others.
+    schedule_defer_count: u32,
+    /// True if `schedule` was called while the task deferred scheduling.
+    schedule_pending: bool,
+    /// False if the task runs in unprivileged thread mode.
+    privileged: bool,
+    /// Number of ticks between two releases of a periodic task, zero if it isn't periodic.
+    #[cfg(feature = "timer")]
+    period: u32,
+    /// The time of the next release of a periodic task.
+    #[cfg(feature = "timer")]
+    next_release: u32,
 }
 
 #[cfg(feature="task_monitor")]
@@ -45,8 +317,80 @@ pub struct TaskControlBlock {
     /// Holds a reference to the stack pointer for the task.
     stack_pointer: usize, // current stack pointer of this thread
     deadline: u32,
+    /// The entry point of the task, it is run from the start on `restart_task`.
+    handler: fn() -> !,
+    /// Address of the lowest word of the task's stack.
+    stack_base: usize,
+    /// Number of words in the task's stack.
+    stack_len: usize,
+    /// A human readable name of the task, for diagnostics.
+    name: Option<&'static str>,
+    /// Index of the lowest painted word of the stack, the words below it are not painted yet.
+    painted_from: usize,
+    /// Nesting depth of the task's `defer_scheduling`, `schedule` only records a pending switch
+    /// while it is non-zero. It is kept per task, so that a task blocking in a deferred region
+    /// doesn't defer the scheduling of the others.
+    schedule_defer_count: u32,
+    /// True if `schedule` was called while the task deferred scheduling.
+    schedule_pending: bool,
+    /// False if the task runs in unprivileged thread mode.
+    privileged: bool,
+    /// Number of ticks between two releases of a periodic task, zero if it isn't periodic.
+    #[cfg(feature = "timer")]
+    period: u32,
+    /// The time of the next release of a periodic task.
+    #[cfg(feature = "timer")]
+    next_release: u32,
+}
+
+
+/// Fills `stack` with `STACK_PAINT`, so that its peak usage can be measured later, and returns the
+/// index of the lowest painted word.
+#[cfg(not(feature = "lazy_stack_paint"))]
+fn paint_stack(stack: &mut [u32]) -> usize {
+    for word in stack.iter_mut() {
+        *word = STACK_PAINT;
+    }
+    0
+}
+
+/// Fills the top `LAZY_PAINT_INITIAL_WORDS` of `stack` with `STACK_PAINT` and returns the index of the
+/// lowest painted word, the idle task paints the rest through `paint_step`.
+#[cfg(feature = "lazy_stack_paint")]
+fn paint_stack(stack: &mut [u32]) -> usize {
+    let painted_from = stack.len().saturating_sub(LAZY_PAINT_INITIAL_WORDS);
+    for word in stack[painted_from..].iter_mut() {
+        *word = STACK_PAINT;
+    }
+    painted_from
 }
 
+/// Returns the task of `candidates` whose turn comes from `cursor`, which is `cursor` itself if it
+/// is a candidate and `include_cursor` is set. Turns go from higher to lower priorities and wrap around.
+#[cfg(feature = "round_robin")]
+fn next_in_band(cursor: usize, candidates: BooleanVector, include_cursor: bool) -> usize {
+    if include_cursor && candidates & (1 << cursor) != 0 {
+        return cursor;
+    }
+    let below = candidates & ((1 << cursor) - 1);
+    let candidates = if below != 0 { below } else { candidates };
+    get_msb(candidates).unwrap()
+}
+
+/// Writes the initial context frame at the top of `stack` so that loading it starts `handler`,
+/// and returns the initial stack pointer.
+fn init_stack_frame(stack: &mut [u32], handler: fn() -> !) -> usize {
+    let pos = stack.len() - 1;
+    let pc: usize = handler as usize;
+
+    stack[pos] = 1 << 24; // xPSR
+    stack[pos - 1] = pc as u32; // PC
+    #[cfg(feature = "fpu")] {
+        stack[stack.len() - CONTEXT_FRAME_WORDS] = 0; // FPSCR
+    }
+
+    unsafe { core::intrinsics::transmute(&stack[stack.len() - CONTEXT_FRAME_WORDS]) }
+}
 
 impl TaskControlBlock {
     pub fn save_context(&self) {
@@ -55,6 +399,10 @@ impl TaskControlBlock {
     pub fn load_context(&self) {
         load_context(self)
     }
+    /// Returns false if the task runs in unprivileged thread mode.
+    pub fn is_privileged(&self) -> bool {
+        self.privileged
+    }
 }
 
 impl Scheduler {
@@ -66,22 +414,37 @@ impl Scheduler {
             started: false,
             task_control_blocks: [None; MAX_TASKS],
             active_tasks: 1,
+            suspended_tasks: 0,
+            ready_queue: ActiveReadyQueue::empty(),
             blocked_tasks: 0,
             is_preemptive: false,
             preempt_disable_count: 0,
+            shutdown: None,
+            context_switches: 0,
+            switch_hint: None,
+            last_switch_reason: None,
+            reserved_priorities: 0,
+            #[cfg(feature = "round_robin")]
+            round_robin_bands: [None; MAX_ROUND_ROBIN_BANDS],
+            #[cfg(feature = "edf_scheduler")]
+            absolute_deadlines: [None; MAX_TASKS],
         }
     }
     
     #[cfg(feature="task_monitor")]
     pub fn init(&mut self) -> Result<(),KernelError>{
         self.is_preemptive = true;
+        // The idle task is active from the start.
+        self.sync_ready_queue(0);
         
-        static mut stack0: [u32; 64] = [0; 64];
+        static mut stack0: [u32; CONTEXT_FRAME_WORDS + 48] = [0; CONTEXT_FRAME_WORDS + 48];
         self.create_task(
             0,
             100,
             unsafe { &mut stack0 },
             || loop {
+                #[cfg(feature = "lazy_stack_paint")]
+                crate::kernel::tasks::paint_stacks();
                 wait_for_interrupt();
             }
         )
@@ -90,12 +453,16 @@ impl Scheduler {
     #[cfg(not(feature="task_monitor"))]
     pub fn init(&mut self) -> Result<(),KernelError>{
         self.is_preemptive = true;
+        // The idle task is active from the start.
+        self.sync_ready_queue(0);
         
-        static mut stack0: [u32; 64] = [0; 64];
+        static mut stack0: [u32; CONTEXT_FRAME_WORDS + 48] = [0; CONTEXT_FRAME_WORDS + 48];
         self.create_task(
             0,
             unsafe { &mut stack0 },
             || loop {
+                #[cfg(feature = "lazy_stack_paint")]
+                crate::kernel::tasks::paint_stacks();
                 wait_for_interrupt();
             }
         )
@@ -115,6 +482,7 @@ impl Scheduler {
         handler_fn: fn() -> !,
     ) -> Result<(), KernelError>
     {
+        self.check_stack_overlap(priority, stack)?;
         let tcb = self.create_tcb(stack, handler_fn)?;
         self.insert_tcb(priority, tcb)
     }
@@ -128,6 +496,7 @@ impl Scheduler {
         handler_fn: fn() -> !,
     ) -> Result<(), KernelError>
     {
+        self.check_stack_overlap(priority, stack)?;
         let tcb = self.create_tcb(deadline, stack, handler_fn)?;
         self.insert_tcb(priority, tcb)
     }
@@ -140,18 +509,25 @@ impl Scheduler {
         handler: fn() -> !,
     ) -> Result<TaskControlBlock, KernelError>
     {
-        if stack.len() < 32 {
+        if stack.len() < CONTEXT_FRAME_WORDS + 16 {
             return Err(KernelError::StackTooSmall);
         }
 
-        let pos = stack.len() - 1;
-        let pc: usize = handler as usize;
-
-        stack[pos] = 1 << 24; // xPSR
-        stack[pos - 1] = pc as u32; // PC
-
-        let stack_pointer: usize = unsafe { core::intrinsics::transmute(&stack[stack.len() - 16]) };
+        let painted_from = paint_stack(stack);
+        let stack_pointer = init_stack_frame(stack, handler);
         let tcb = TaskControlBlock {
+            handler,
+            stack_base: stack.as_ptr() as usize,
+            stack_len: stack.len(),
+            name: None,
+            painted_from,
+            schedule_defer_count: 0,
+            schedule_pending: false,
+            privileged: true,
+            #[cfg(feature = "timer")]
+            period: 0,
+            #[cfg(feature = "timer")]
+            next_release: 0,
             stack_pointer: stack_pointer as usize 
         };
 
@@ -166,18 +542,25 @@ impl Scheduler {
         handler: fn() -> !,
     ) -> Result<TaskControlBlock, KernelError>
     {
-        if stack.len() < 32 {
+        if stack.len() < CONTEXT_FRAME_WORDS + 16 {
             return Err(KernelError::StackTooSmall);
         }
 
-        let pos = stack.len() - 1;
-        let pc: usize = handler as usize;
-
-        stack[pos] = 1 << 24; // xPSR
-        stack[pos - 1] = pc as u32; // PC
-
-        let stack_pointer: usize = unsafe { core::intrinsics::transmute(&stack[stack.len() - 16]) };
+        let painted_from = paint_stack(stack);
+        let stack_pointer = init_stack_frame(stack, handler);
         let tcb = TaskControlBlock {
+            handler,
+            stack_base: stack.as_ptr() as usize,
+            stack_len: stack.len(),
+            name: None,
+            painted_from,
+            schedule_defer_count: 0,
+            schedule_pending: false,
+            privileged: true,
+            #[cfg(feature = "timer")]
+            period: 0,
+            #[cfg(feature = "timer")]
+            next_release: 0,
             deadline, 
             stack_pointer: stack_pointer as usize 
         };
@@ -185,6 +568,23 @@ impl Scheduler {
         Ok(tcb)
     }
 
+    /// Returns `KernelError::StackOverlap` if `stack` overlaps the stack of any task other than `id`.
+    /// It is checked before the initial context is written, which would corrupt the other task's stack.
+    fn check_stack_overlap(&self, id: usize, stack: &[u32]) -> Result<(), KernelError> {
+        let start = stack.as_ptr() as usize;
+        let end = start + stack.len() * 4;
+        for (tid, other) in self.task_control_blocks.iter().enumerate() {
+            if let Some(other) = other {
+                let other_start = other.stack_base;
+                let other_end = other_start + other.stack_len * 4;
+                if tid != id && start < other_end && other_start < end {
+                    return Err(KernelError::StackOverlap);
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Inserts the `TCB` into `task_control_blocks` at position `id`.
     fn insert_tcb(&mut self, id: usize, tcb: TaskControlBlock) -> Result<(), KernelError> {
         if id >= MAX_TASKS {
@@ -194,34 +594,525 @@ impl Scheduler {
         return Ok(());
     }
 
+    /// Returns the lowest reserved priority which has no task yet.
+    pub fn free_reserved_priority(&self) -> Option<usize> {
+        (0..MAX_TASKS).find(|&tid| self.reserved_priorities & (1 << tid) != 0 && self.task_control_blocks[tid].is_none())
+    }
+
+    /// Sets the name of the task `tid`.
+    pub fn set_task_name(&mut self, tid: usize, name: &'static str) -> Result<(), KernelError> {
+        let tcb = self.task_control_blocks.get_mut(tid).and_then(|tcb| tcb.as_mut()).ok_or(KernelError::NotFound)?;
+        tcb.name = Some(name);
+        Ok(())
+    }
+
+    /// Returns the configuration of the task `tid`, `None` if it wasn't created.
+    pub fn task_info(&self, tid: usize) -> Option<TaskInfo> {
+        self.task_control_blocks.get(tid).and_then(|tcb| tcb.as_ref()).map(|tcb| TaskInfo {
+            priority: tid as TaskId,
+            #[cfg(feature="task_monitor")]
+            deadline: tcb.deadline,
+            stack_len: tcb.stack_len,
+            name: tcb.name,
+        })
+    }
+
+    /// Returns the address of the lowest word of the stack of the task `tid`.
+    pub fn stack_base(&self, tid: usize) -> Option<usize> {
+        self.task_control_blocks.get(tid).and_then(|tcb| tcb.as_ref()).map(|tcb| tcb.stack_base)
+    }
+
+    /// Returns the peak number of words used on the stack of the task `tid`, counting the words
+    /// which no longer hold `STACK_PAINT` from the top of the stack.
+    pub fn stack_watermark(&self, tid: usize) -> Option<usize> {
+        self.task_control_blocks.get(tid).and_then(|tcb| tcb.as_ref()).map(|tcb| {
+            let stack = unsafe { core::slice::from_raw_parts(tcb.stack_base as *const u32, tcb.stack_len) };
+            let unused = stack[tcb.painted_from..].iter().take_while(|&&word| word == STACK_PAINT).count();
+            // The words below `painted_from` are unused only if the task never reached the painted boundary,
+            // otherwise nothing is known about them and the whole stack is counted as used.
+            match unused {
+                0 => tcb.stack_len,
+                unused => tcb.stack_len - tcb.painted_from - unused,
+            }
+        })
+    }
+
+    /// Paints `LAZY_PAINT_STEP_WORDS` more words at the bottom of every stack which isn't fully painted
+    /// yet, called by the idle task. A stack whose task already reached its painted boundary is left as
+    /// is, since painting over words it used would hide them from the watermark. The running task's
+    /// stack is skipped.
+    ///
+    /// The saved stack pointer of a switched out task bounds the painting: its context frame may have
+    /// been pushed past the boundary without writing the boundary word itself, so the word alone doesn't
+    /// tell whether the words below the boundary are free. Only the words below the stack pointer are.
+    #[cfg(feature = "lazy_stack_paint")]
+    pub fn paint_step(&mut self) {
+        let curr_tid = self.curr_tid;
+        for (tid, tcb) in self.task_control_blocks.iter_mut().enumerate() {
+            if let Some(tcb) = tcb {
+                if tid == curr_tid || tcb.painted_from == 0 {
+                    continue;
+                }
+                let live_from = tcb.stack_pointer.saturating_sub(tcb.stack_base) / 4;
+                if live_from <= tcb.painted_from {
+                    continue;
+                }
+                let stack = unsafe { core::slice::from_raw_parts_mut(tcb.stack_base as *mut u32, tcb.stack_len) };
+                if stack[tcb.painted_from] != STACK_PAINT {
+                    continue;
+                }
+                let painted_from = tcb.painted_from.saturating_sub(LAZY_PAINT_STEP_WORDS);
+                for word in stack[painted_from..tcb.painted_from].iter_mut() {
+                    *word = STACK_PAINT;
+                }
+                tcb.painted_from = painted_from;
+            }
+        }
+    }
+
+    /// Reduces the stack of the task `tid` to its top `new_len` words and returns the freed words at
+    /// the bottom. Returns `KernelError::StackTooSmall` if the task has used more than `new_len` words
+    /// or `new_len` is below the minimum stack size.
+    ///
+    /// # Safety
+    /// The watermark only proves the task hasn't used the freed words so far, the caller must be sure
+    /// the task never needs more than `new_len` words on any later path.
+    pub unsafe fn shrink_stack(&mut self, tid: usize, new_len: usize) -> Result<&'static mut [u32], KernelError> {
+        let used = self.stack_watermark(tid).ok_or(KernelError::NotFound)?;
+        let tcb = self.task_control_blocks[tid].as_mut().unwrap();
+        if new_len > tcb.stack_len {
+            return Err(KernelError::LimitExceeded);
+        }
+        if new_len < used || new_len < CONTEXT_FRAME_WORDS + 16 {
+            return Err(KernelError::StackTooSmall);
+        }
+        let freed_len = tcb.stack_len - new_len;
+        let freed = core::slice::from_raw_parts_mut(tcb.stack_base as *mut u32, freed_len);
+        tcb.stack_base += freed_len * 4;
+        tcb.stack_len = new_len;
+        tcb.painted_from = tcb.painted_from.saturating_sub(freed_len);
+        Ok(freed)
+    }
+
+    /// Replaces the entry point of the task `tid`, the new handler runs from the next `restart_task`.
+    /// The running task can't replace its own handler.
+    pub fn set_task_handler(&mut self, tid: usize, handler: fn() -> !) -> Result<(), KernelError> {
+        if self.started && tid == self.curr_tid {
+            return Err(KernelError::AccessDenied);
+        }
+        let tcb = self.task_control_blocks.get_mut(tid).and_then(|tcb| tcb.as_mut()).ok_or(KernelError::NotFound)?;
+        tcb.handler = handler;
+        Ok(())
+    }
+
+    /// Discards the context of the task `tid` and releases it, so that it runs its handler from the
+    /// start. The running task can't be restarted.
+    pub fn restart_task(&mut self, tid: usize) -> Result<(), KernelError> {
+        if self.started && tid == self.curr_tid {
+            return Err(KernelError::AccessDenied);
+        }
+        let tcb = self.task_control_blocks.get_mut(tid).and_then(|tcb| tcb.as_mut()).ok_or(KernelError::NotFound)?;
+        let stack = unsafe { core::slice::from_raw_parts_mut(tcb.stack_base as *mut u32, tcb.stack_len) };
+        tcb.stack_pointer = init_stack_frame(stack, tcb.handler);
+        tcb.schedule_defer_count = 0;
+        tcb.schedule_pending = false;
+        self.release(1 << tid);
+        Ok(())
+    }
+
+    /// Returns the state of the task `tid`. Once the kernel started exactly one task is `Running`, the one
+    /// on the CPU, even if it has just blocked or exited and the switch away from it is pending.
+    pub fn task_state(&self, tid: usize) -> TaskState {
+        if tid >= MAX_TASKS || self.task_control_blocks[tid].is_none() {
+            return TaskState::Inactive;
+        }
+        if self.started && tid == self.curr_tid {
+            TaskState::Running
+        } else if self.active_tasks & (1 << tid) == 0 {
+            TaskState::Inactive
+        } else if self.suspended_tasks & (1 << tid) != 0 {
+            TaskState::Suspended
+        } else if self.blocked_tasks & (1 << tid) != 0 {
+            TaskState::Blocked
+        } else {
+            TaskState::Ready
+        }
+    }
+
+    /// Removes the task `tid` from every mask and frees its slot, so that a later `create_task` or
+    /// `spawn_dynamic` can take its priority. The idle task and the running task can't be deleted.
+    pub fn delete_task(&mut self, tid: usize) -> Result<(), KernelError> {
+        if tid == 0 || (self.started && tid == self.curr_tid) {
+            return Err(KernelError::AccessDenied);
+        }
+        if tid >= MAX_TASKS || self.task_control_blocks[tid].is_none() {
+            return Err(KernelError::NotFound);
+        }
+        let before = self.ready_tasks();
+        self.task_control_blocks[tid] = None;
+        self.active_tasks &= !(1 << tid);
+        self.blocked_tasks &= !(1 << tid);
+        self.suspended_tasks &= !(1 << tid);
+        self.sync_ready_queue(before);
+        #[cfg(feature = "edf_scheduler")] {
+            self.absolute_deadlines[tid] = None;
+        }
+        Ok(())
+    }
+
+    /// Moves the task `old_tid` to the free priority `new_priority`, along with its bits in
+    /// `active_tasks` and `suspended_tasks`. Blocked tasks can't be moved, as the primitive they wait
+    /// on unblocks them by their old TaskId.
+    pub fn set_task_priority(&mut self, old_tid: usize, new_priority: usize) -> Result<(), KernelError> {
+        if old_tid == 0 || new_priority == 0 {
+            return Err(KernelError::AccessDenied);
+        }
+        if old_tid >= MAX_TASKS || new_priority >= MAX_TASKS || self.task_control_blocks[old_tid].is_none() {
+            return Err(KernelError::NotFound);
+        }
+        if self.task_control_blocks[new_priority].is_some() {
+            return Err(KernelError::Exists);
+        }
+        if self.blocked_tasks & (1 << old_tid) != 0 {
+            return Err(KernelError::AccessDenied);
+        }
+        self.task_control_blocks[new_priority] = self.task_control_blocks[old_tid].take();
+        #[cfg(feature = "edf_scheduler")] {
+            self.absolute_deadlines[new_priority] = self.absolute_deadlines[old_tid].take();
+        }
+        let before = self.ready_tasks();
+        for mask in [&mut self.active_tasks, &mut self.suspended_tasks].iter_mut() {
+            if **mask & (1 << old_tid) != 0 {
+                **mask = (**mask & !(1 << old_tid)) | (1 << new_priority);
+            }
+        }
+        self.sync_ready_queue(before);
+        if self.curr_tid == old_tid {
+            self.curr_tid = new_priority;
+        }
+        Ok(())
+    }
+
+    /// Sets whether the task `tid` runs in privileged thread mode, applied from its next switch in.
+    pub fn set_privileged(&mut self, tid: usize, privileged: bool) -> Result<(), KernelError> {
+        let tcb = self.task_control_blocks.get_mut(tid).and_then(|tcb| tcb.as_mut()).ok_or(KernelError::NotFound)?;
+        tcb.privileged = privileged;
+        Ok(())
+    }
+
+    /// Makes the task `tid` periodic, it is released every `period` ticks from `now` on.
+    /// A zero `period` makes it aperiodic again.
+    #[cfg(feature = "timer")]
+    pub fn set_period(&mut self, tid: usize, period: u32, now: u32) -> Result<(), KernelError> {
+        let tcb = self.task_control_blocks.get_mut(tid).and_then(|tcb| tcb.as_mut()).ok_or(KernelError::NotFound)?;
+        tcb.period = period;
+        tcb.next_release = now.wrapping_add(period);
+        Ok(())
+    }
+
+    /// Returns the periodic tasks due for release at `now` and schedules their next release.
+    #[cfg(feature = "timer")]
+    pub fn take_due_periodic(&mut self, now: u32) -> BooleanVector {
+        let mut due = 0;
+        for (tid, tcb) in self.task_control_blocks.iter_mut().enumerate() {
+            if let Some(tcb) = tcb {
+                if tcb.period > 0 && now.wrapping_sub(tcb.next_release) as i32 >= 0 {
+                    tcb.next_release = tcb.next_release.wrapping_add(tcb.period);
+                    due |= 1 << tid;
+                }
+            }
+        }
+        due
+    }
+
+    /// Enters a `defer_scheduling` region of the running task.
+    pub fn defer_scheduling(&mut self) {
+        if let Some(tcb) = self.task_control_blocks[self.curr_tid].as_mut() {
+            tcb.schedule_defer_count += 1;
+        }
+    }
+
+    /// Leaves a `defer_scheduling` region of the running task, returns true if it was the outermost
+    /// one and a switch was requested within it.
+    pub fn resume_scheduling(&mut self) -> bool {
+        match self.task_control_blocks[self.curr_tid].as_mut() {
+            Some(tcb) => {
+                tcb.schedule_defer_count = tcb.schedule_defer_count.saturating_sub(1);
+                tcb.schedule_defer_count == 0 && core::mem::replace(&mut tcb.schedule_pending, false)
+            }
+            None => false,
+        }
+    }
+
+    /// Records a requested switch as pending and returns true if the running task deferred
+    /// scheduling. A task which blocked itself must be switched out even when deferring, it can't
+    /// make progress.
+    pub fn defer_switch(&mut self) -> bool {
+        let is_ready = self.is_ready(self.curr_tid);
+        match self.task_control_blocks[self.curr_tid].as_mut() {
+            Some(tcb) if tcb.schedule_defer_count > 0 && is_ready => {
+                tcb.schedule_pending = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
     /// Appends `tasks_mask` onto `blocked_tasks`.
     pub fn block_tasks(&mut self, tasks_mask: BooleanVector) {
+        let before = self.ready_tasks();
         self.blocked_tasks |= tasks_mask;
+        self.sync_ready_queue(before);
     }
 
     /// Removes `tasks_mask` from `blocked_tasks`.
     pub fn unblock_tasks(&mut self, tasks_mask: BooleanVector) {
+        let before = self.ready_tasks();
         self.blocked_tasks &= !tasks_mask;
+        self.sync_ready_queue(before);
+    }
+
+    /// Adds `tasks_mask` onto `suspended_tasks`.
+    pub fn suspend_tasks(&mut self, tasks_mask: BooleanVector) {
+        let before = self.ready_tasks();
+        self.suspended_tasks |= tasks_mask;
+        self.sync_ready_queue(before);
+    }
+
+    /// Removes `tasks_mask` from `suspended_tasks`.
+    pub fn resume_tasks(&mut self, tasks_mask: BooleanVector) {
+        let before = self.ready_tasks();
+        self.suspended_tasks &= !tasks_mask;
+        self.sync_ready_queue(before);
+    }
+
+    /// Removes `tasks_mask` from `active_tasks`.
+    pub fn deactivate_tasks(&mut self, tasks_mask: BooleanVector) {
+        let before = self.ready_tasks();
+        self.active_tasks &= !tasks_mask;
+        self.sync_ready_queue(before);
+    }
+
+    /// Returns the tasks which have been created, that is the TaskIds with a task control block.
+    pub fn created_tasks(&self) -> BooleanVector {
+        let mut created = 0;
+        for (tid, tcb) in self.task_control_blocks.iter().enumerate() {
+            if tcb.is_some() {
+                created |= 1 << tid;
+            }
+        }
+        created
+    }
+
+    /// Returns the tasks which are active, and neither blocked nor suspended.
+    pub fn ready_tasks(&self) -> BooleanVector {
+        self.active_tasks & !self.blocked_tasks & !self.suspended_tasks
     }
 
     /// Returns the TaskId currently high priority task, which is in ready state.
-    /// The highest priority is determined by calculating the most significant bit of boolean vector
-    /// corresponding to the tasks in the ready state. The tasks in the ready state can be identified
-    /// by the boolean and of `active_tasks` and boolean not(`blocked_tasks`).
+    /// The highest priority is determined by the ready queue (by default the most significant bit of
+    /// the boolean vector) corresponding to the tasks in the ready state. The tasks in the ready state can be identified
+    /// by the boolean and of `active_tasks`, boolean not(`blocked_tasks`) and boolean not(`suspended_tasks`).
+    ///
+    /// The idle task (TaskId 0) is always active and never blocked, hence it is chosen only when no
+    /// other task is ready. Any ready task, even of priority 1, runs instead of it and is preempted as
+    /// soon as a higher priority task gets ready, as every routine readying a task reschedules.
+    ///
+    /// With the `edf_scheduler` feature the ready task with the nearest absolute deadline is picked
+    /// instead, the tasks without a deadline only run when no task with one is ready. Resource ceilings
+    /// are still based on priorities.
     pub fn get_next_tid(&self) -> usize {
-        let mask = self.active_tasks & !self.blocked_tasks;
-        return get_msb(mask).unwrap();
+        #[cfg(feature = "edf_scheduler")] {
+            if let Some(tid) = self.earliest_deadline(self.ready_tasks()) {
+                return tid;
+            }
+        }
+        let tid = self.ready_queue.peek_highest().unwrap();
+        #[cfg(feature = "round_robin")] {
+            if let Some(band) = self.round_robin_band(tid) {
+                return next_in_band(band.cursor, self.ready_tasks() & band.tasks, true);
+            }
+        }
+        return tid;
+    }
+
+    /// Brings the ready queue in step with the masks, `before` being the ready tasks before they changed.
+    fn sync_ready_queue(&mut self, before: BooleanVector) {
+        let after = self.ready_tasks();
+        let changed = before ^ after;
+        for tid in 0..MAX_TASKS {
+            if changed & (1 << tid) != 0 {
+                if after & (1 << tid) != 0 {
+                    self.ready_queue.insert(tid);
+                } else {
+                    self.ready_queue.remove(tid);
+                }
+            }
+        }
+    }
+
+    /// Returns the task of `tasks_mask` with the nearest absolute deadline, the higher priority one on
+    /// a tie. `None` if no task of the mask has a deadline, the idle task never has one.
+    #[cfg(feature = "edf_scheduler")]
+    fn earliest_deadline(&self, tasks_mask: BooleanVector) -> Option<usize> {
+        let mut earliest: Option<(usize, u32)> = None;
+        for tid in (1..MAX_TASKS).rev() {
+            if tasks_mask & (1 << tid) == 0 {
+                continue;
+            }
+            if let Some(deadline) = self.absolute_deadlines[tid] {
+                // Compared through the wrapping difference, so that deadlines past a wrap of the time stay later.
+                let is_earlier = earliest.map_or(true, |(_, best)| (deadline.wrapping_sub(best) as i32) < 0);
+                if is_earlier {
+                    earliest = Some((tid, deadline));
+                }
+            }
+        }
+        earliest.map(|(tid, _)| tid)
+    }
+
+    /// Sets the priorities in `tasks_mask` as a round-robin band with a turn of `quantum` ticks,
+    /// or updates the quantum if the band already exists.
+    #[cfg(feature = "round_robin")]
+    pub fn enable_round_robin(&mut self, tasks_mask: BooleanVector, quantum: u32) -> Result<(), KernelError> {
+        if tasks_mask & 1 != 0 || tasks_mask.count_ones() < 2 {
+            return Err(KernelError::AccessDenied);
+        }
+        // The priorities must be consecutive, otherwise a task in between would run after the band.
+        let shifted = tasks_mask >> tasks_mask.trailing_zeros();
+        if shifted & (shifted + 1) != 0 {
+            return Err(KernelError::AccessDenied);
+        }
+        let quantum = core::cmp::max(quantum, 1);
+        for band in self.round_robin_bands.iter_mut().flatten() {
+            if band.tasks == tasks_mask {
+                band.quantum = quantum;
+                return Ok(());
+            }
+            if band.tasks & tasks_mask != 0 {
+                return Err(KernelError::Exists);
+            }
+        }
+        let slot = self.round_robin_bands.iter_mut().find(|band| band.is_none()).ok_or(KernelError::LimitExceeded)?;
+        *slot = Some(RoundRobinBand {
+            tasks: tasks_mask,
+            quantum,
+            ticks_left: quantum,
+            cursor: (31 - tasks_mask.leading_zeros()) as usize,
+        });
+        Ok(())
+    }
+
+    /// Returns the round-robin band of the task `tid`, if any.
+    #[cfg(feature = "round_robin")]
+    fn round_robin_band(&self, tid: usize) -> Option<&RoundRobinBand> {
+        self.round_robin_bands.iter().flatten().find(|band| band.tasks & (1 << tid) != 0)
     }
 
-    /// Updates `active_tasks` with `task_mask`.
+    /// Accounts a tick to the turn of the running task, if it is in a band. Returns true if its
+    /// quantum expired and the turn passed to another ready task of the band.
+    #[cfg(feature = "round_robin")]
+    pub fn round_robin_tick(&mut self) -> bool {
+        let curr_tid = self.curr_tid;
+        let ready = self.ready_tasks();
+        let band = match self.round_robin_bands.iter_mut().flatten().find(|band| band.tasks & (1 << curr_tid) != 0) {
+            Some(band) => band,
+            None => return false,
+        };
+        if band.cursor != curr_tid {
+            band.cursor = curr_tid;
+            band.ticks_left = band.quantum;
+        }
+        band.ticks_left -= 1;
+        if band.ticks_left > 0 {
+            return false;
+        }
+        band.ticks_left = band.quantum;
+        let others = ready & band.tasks & !(1 << curr_tid);
+        if others == 0 {
+            return false;
+        }
+        band.cursor = next_in_band(curr_tid, others, false);
+        self.switch_hint = Some(SwitchReason::QuantumExpired);
+        true
+    }
+
+    /// Returns a copy of the scheduler state.
+    pub fn snapshot(&self) -> SchedulerSnapshot {
+        SchedulerSnapshot {
+            curr_tid: self.curr_tid as TaskId,
+            started: self.started,
+            active_tasks: self.active_tasks,
+            blocked_tasks: self.blocked_tasks,
+            is_preemptive: self.is_preemptive,
+            system_ceiling: None,
+        }
+    }
+
+    /// Returns true if the task is active, and neither blocked nor suspended.
+    pub fn is_ready(&self, tid: usize) -> bool {
+        self.ready_tasks() & (1 << tid) != 0
+    }
+
+    /// Verifies the consistency of the task state. `blocked_tasks` is not required to be a subset of
+    /// `active_tasks`, as the ceiling protocol blocks tasks irrespective of them being active.
+    pub fn check_invariants(&self) -> Result<(), InvariantViolation> {
+        if self.started && !self.is_ready(self.curr_tid) {
+            return Err(InvariantViolation::CurrentTaskNotReady);
+        }
+        if self.task_control_blocks[0].is_some() && !self.is_ready(0) {
+            return Err(InvariantViolation::IdleTaskNotReady);
+        }
+        for tid in 0..32 {
+            if self.active_tasks & (1 << tid) != 0 && (tid >= MAX_TASKS || self.task_control_blocks[tid].is_none()) {
+                return Err(InvariantViolation::ActiveTaskWithoutTcb);
+            }
+        }
+        let highest = match self.ready_tasks() {
+            0 => None,
+            ready => Some(31 - ready.leading_zeros() as usize),
+        };
+        if self.ready_queue.peek_highest() != highest {
+            return Err(InvariantViolation::ReadyQueueMismatch);
+        }
+        Ok(())
+    }
+
+    /// Updates `active_tasks` with `task_mask`. Tasks created with a zero deadline are not monitored.
     pub fn release(&mut self, tasks_mask: BooleanVector) {
+        // Primitives may still name a deleted task, only created tasks are released.
+        let tasks_mask = tasks_mask & self.created_tasks();
         #[cfg(feature = "task_monitor")] {
-            for i in 0..32 {
+            for i in 0..MAX_TASKS {
                 if (tasks_mask & 1<<i) > 0 {
-                    set_deadline(i as TaskId, self.task_control_blocks[i].unwrap().deadline)
+                    let deadline = match self.task_control_blocks[i] {
+                        Some(tcb) => tcb.deadline,
+                        None => continue,
+                    };
+                    if deadline > 0 {
+                        set_deadline(i as TaskId, deadline)
+                    }
+                    #[cfg(feature = "edf_scheduler")] {
+                        self.absolute_deadlines[i] = if deadline > 0 { Some(get_time().wrapping_add(deadline)) } else { None };
+                    }
                 }
             }
         }
+        let before = self.ready_tasks();
         self.active_tasks |= tasks_mask;
+        self.sync_ready_queue(before);
+        self.switch_hint = Some(SwitchReason::Released);
+    }
+
+    /// Returns the reason the running task `curr_tid` is being switched out. Exiting and blocking are
+    /// read off the task's state, otherwise the reason given by the routine which rescheduled is used.
+    pub fn switch_reason(&self, curr_tid: usize) -> SwitchReason {
+        if self.active_tasks & (1 << curr_tid) == 0 {
+            SwitchReason::Exited
+        } else if !self.is_ready(curr_tid) {
+            SwitchReason::Blocked
+        } else {
+            self.switch_hint.unwrap_or(SwitchReason::Preempted)
+        }
     }
 }
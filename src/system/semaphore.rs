@@ -1,11 +1,14 @@
 //! # Software synchronization bus definition
 //!
 use core::cell::RefCell;
-use crate::system::scheduler::BooleanVector;
+use crate::system::scheduler::{BlockReason, BooleanVector};
 use crate::KernelError;
-use crate::kernel::tasks::{get_curr_tid, release, schedule};
+use crate::kernel::tasks::{block_tasks_including_self, get_curr_tid, release_tasks, schedule, wake_tasks};
 use crate::utils::arch::critical_section;
 
+#[cfg(feature = "timer")]
+use crate::kernel::timer::{set_timeout, clear_timeout, take_timed_out};
+
 #[cfg(feature = "system_logger")]
 use {
     crate::system::system_logger::LogEventType,
@@ -18,31 +21,211 @@ pub struct Semaphore {
     pub flags: RefCell<BooleanVector>,
     /// It is a boolean vector that corresponds to the tasks that are to be released by the semaphore on being signaled.
     pub tasks: BooleanVector,
+    /// It is a boolean vector of the tasks currently blocked in `wait`.
+    waiters: RefCell<BooleanVector>,
+    /// The semaphore signaled, with its tasks mask, whenever this one is signaled.
+    downstream: RefCell<Option<(&'static Semaphore, BooleanVector)>>,
 }
 
 impl Semaphore {
     /// Initializes a new semaphore instance.
     pub const fn new(tasks: BooleanVector) -> Self {
-        Self { flags: RefCell::new(0), tasks }
+        Self { flags: RefCell::new(0), tasks, waiters: RefCell::new(0), downstream: RefCell::new(None) }
     }
 
     /// Signals the semaphore, all tasks specified in semaphore::flags can test for it and all tasks in semaphore::tasks are released
     pub fn signal_and_release(&'static self, tasks_mask: BooleanVector) {
+        #[cfg(feature = "system_logger")] {
+            let unreleasable = tasks_mask & !self.releasable();
+            if unreleasable != 0 && logging::get_semaphore_signal() {
+                logging::report(LogEventType::SemaphoreUnreleasable(unreleasable));
+            }
+        }
+        self.signal(tasks_mask);
+        schedule();
+    }
+
+    /// Same as `signal_and_release`, but returns `KernelError::InvalidTask` without signaling if
+    /// `tasks_mask` flags a task which is neither released by the semaphore nor waiting on it, as
+    /// such a task would never be woken to consume its flag.
+    /// The check and the signal happen in one critical section, so the waiters can't change in between.
+    pub fn try_signal_and_release(&'static self, tasks_mask: BooleanVector) -> Result<(), KernelError> {
         critical_section(|_| {
-            let flags: &mut BooleanVector = &mut self.flags.borrow_mut();
-            *flags |= tasks_mask;
-            release(self.tasks);
+            if tasks_mask & !self.releasable() != 0 {
+                return Err(KernelError::InvalidTask);
+            }
+            self.signal(tasks_mask);
+            Ok(())
+        })?;
+        schedule();
+        Ok(())
+    }
+
+    /// Returns the tasks a signal can wake, the tasks released by the semaphore and its waiters.
+    fn releasable(&self) -> BooleanVector {
+        critical_section(|_| self.tasks | *self.waiters.borrow())
+    }
+
+    /// Signals every semaphore in `group` with its tasks mask in a single critical section and
+    /// reschedules once after all of them are signaled, instead of once per semaphore.
+    pub fn signal_group(group: &[(&'static Semaphore, BooleanVector)]) {
+        critical_section(|_| {
+            for (semaphore, tasks_mask) in group.iter() {
+                semaphore.signal(*tasks_mask);
+            }
+        });
+        schedule();
+    }
+
+    /// Makes every signal of this semaphore also signal `downstream` with `tasks_mask`, in the same
+    /// critical section, replacing any previous chain. Returns `KernelError::CycleDetected` if
+    /// `downstream` already leads back to this semaphore.
+    pub fn chain(&'static self, downstream: &'static Semaphore, tasks_mask: BooleanVector) -> Result<(), KernelError> {
+        critical_section(|_| {
+            let mut next = Some(downstream);
+            while let Some(semaphore) = next {
+                if core::ptr::eq(semaphore, self) {
+                    return Err(KernelError::CycleDetected);
+                }
+                next = semaphore.downstream.borrow().map(|(semaphore, _)| semaphore);
+            }
+            self.downstream.replace(Some((downstream, tasks_mask)));
+            Ok(())
+        })
+    }
+
+    /// Sets the flag of every task of the semaphore and releases them all, for notifying all of them
+    /// of a common change. It is the same as `signal_and_release(self.tasks)`, but is logged as a broadcast.
+    pub fn signal_all(&'static self) {
+        critical_section(|_| {
+            self.update(self.tasks);
             #[cfg(feature = "system_logger")] {
                 if logging::get_semaphore_signal() {
-                    logging::report(LogEventType::SemaphoreSignal(*flags, self.tasks));
+                    logging::report(LogEventType::SemaphoreBroadcast(self.tasks));
                 }
             }
-            schedule();
+            self.signal_downstream();
+        });
+        schedule();
+    }
+
+    /// Updates the flags and releases the tasks without rescheduling, the caller must call `schedule` afterwards.
+    pub(crate) fn signal(&self, tasks_mask: BooleanVector) {
+        critical_section(|_| {
+            #[allow(unused_variables)]
+            let flags = self.update(tasks_mask);
+            #[cfg(feature = "system_logger")] {
+                if logging::get_semaphore_signal() {
+                    logging::report(LogEventType::SemaphoreSignal(flags, self.tasks));
+                }
+            }
+            self.signal_downstream();
         })
     }
 
-    /// Checks if the flag was enabled for the currently running task.
+    /// Sets `tasks_mask` in the flags, releases the tasks and wakes the waiters whose flag is set.
+    /// Returns the new flags.
+    fn update(&self, tasks_mask: BooleanVector) -> BooleanVector {
+        let flags: &mut BooleanVector = &mut self.flags.borrow_mut();
+        *flags |= tasks_mask;
+        release_tasks(self.tasks);
+        let waiters: &mut BooleanVector = &mut self.waiters.borrow_mut();
+        let woken = *waiters & *flags;
+        *waiters &= !woken;
+        wake_tasks(woken);
+        *flags
+    }
+
+    /// Signals the chained semaphore, if any.
+    fn signal_downstream(&self) {
+        if let Some((downstream, tasks_mask)) = *self.downstream.borrow() {
+            downstream.signal(tasks_mask);
+        }
+    }
+
+    /// Blocks the currently running task until its flag is set, then resets the flag. Returns
+    /// immediately if the flag is already set.
+    pub fn wait(&'static self) {
+        loop {
+            let is_set = critical_section(|_| {
+                let curr_tid_mask = 1 << get_curr_tid();
+                let flags: &mut BooleanVector = &mut self.flags.borrow_mut();
+                if *flags & curr_tid_mask == curr_tid_mask {
+                    *flags &= !curr_tid_mask;
+                    return true;
+                }
+                *self.waiters.borrow_mut() |= curr_tid_mask;
+                block_tasks_including_self(curr_tid_mask, BlockReason::Semaphore);
+                false
+            });
+            if is_set {
+                return;
+            }
+            schedule();
+        }
+    }
+
+    /// Same as `wait`, but gives up after `ticks` ticks. Returns `Ok(true)` if the flag was set, and
+    /// reset, and `Ok(false)` if the timeout expired first.
+    ///
+    /// The flag is tested before the timeout each time the task runs, hence a signal arriving on the
+    /// same tick as the timeout, before the task is scheduled again, is always reported as a signal.
+    #[cfg(feature = "timer")]
+    pub fn wait_timeout(&'static self, ticks: u32) -> Result<bool, KernelError> {
+        let curr_tid = get_curr_tid();
+        let curr_tid_mask = 1 << curr_tid;
+        set_timeout(curr_tid, ticks);
+        loop {
+            let res = critical_section(|_| {
+                let flags: &mut BooleanVector = &mut self.flags.borrow_mut();
+                if *flags & curr_tid_mask == curr_tid_mask {
+                    *flags &= !curr_tid_mask;
+                    return Some(true);
+                }
+                if take_timed_out(curr_tid) {
+                    *self.waiters.borrow_mut() &= !curr_tid_mask;
+                    return Some(false);
+                }
+                *self.waiters.borrow_mut() |= curr_tid_mask;
+                block_tasks_including_self(curr_tid_mask, BlockReason::Semaphore);
+                None
+            });
+            match res {
+                Some(is_set) => {
+                    clear_timeout(curr_tid);
+                    return Ok(is_set);
+                }
+                None => schedule(),
+            }
+        }
+    }
+
+    /// Returns the flags which are set and not yet reset by their tasks.
+    pub fn pending_flags(&'static self) -> BooleanVector {
+        critical_section(|_| *self.flags.borrow())
+    }
+
+    /// Returns the tasks currently blocked in `wait` on this semaphore.
+    pub fn waiting_tasks(&'static self) -> BooleanVector {
+        critical_section(|_| *self.waiters.borrow())
+    }
+
+    /// Checks if the flag was enabled for the currently running task. Same as `try_wait`, it never blocks.
     pub fn test_and_reset(&'static self) -> Result<bool, KernelError> {
+        Ok(self.try_wait())
+    }
+
+    /// Returns true if the flag of the currently running task is set, without resetting it.
+    pub fn peek(&'static self) -> bool {
+        critical_section(|_| {
+            let curr_tid_mask = 1 << get_curr_tid();
+            *self.flags.borrow() & curr_tid_mask == curr_tid_mask
+        })
+    }
+
+    /// Resets the flag of the currently running task and returns true if it was set. Unlike `wait`,
+    /// it returns false immediately if the flag isn't set.
+    pub fn try_wait(&'static self) -> bool {
         critical_section(|_| {
             let curr_tid = get_curr_tid() as u32;
             let curr_tid_mask = 1 << curr_tid;
@@ -54,12 +237,12 @@ impl Semaphore {
                         logging::report(LogEventType::SemaphoreReset(curr_tid));
                     }
                 }
-                return Ok(true);
+                return true;
             } else {
-                return Ok(false);
+                return false;
             }
-    })
-}
+        })
+    }
 }
 
 unsafe impl Sync for Semaphore {}
\ No newline at end of file
@@ -20,8 +20,44 @@ pub enum LogEventType {
     SemaphoreSignal(BooleanVector,BooleanVector),
     SemaphoreReset(TaskId),
     DeadlineExpired(TaskId,u32),
+    CeilingStack(usize,TaskId),
+    TaskStarvation(TaskId),
     #[cfg(any(feature = "events_32", feature = "events_16", feature = "events_64"))]
     TimerEvent(EventId),
+    SemaphoreUnreleasable(BooleanVector),
+    BlockedTooLong(TaskId),
+    SemaphoreBroadcast(BooleanVector),
+    SuspendTasks(BooleanVector),
+    ResumeTasks(BooleanVector),
+}
+
+impl LogEventType {
+    /// Encodes the event as `[code, first argument, second argument]`, the code is the position of
+    /// the variant in the declaration and unused arguments are zero.
+    pub fn to_words(&self) -> [u32; 3] {
+        match *self {
+            LogEventType::ReleaseTasks(tasks_mask) => [0, tasks_mask, 0],
+            LogEventType::BlockTasks(tasks_mask) => [1, tasks_mask, 0],
+            LogEventType::UnblockTasks(tasks_mask) => [2, tasks_mask, 0],
+            LogEventType::TaskExit(tasks_mask) => [3, tasks_mask, 0],
+            LogEventType::ResourceLock(task_id) => [4, task_id, 0],
+            LogEventType::ResourceUnlock(task_id) => [5, task_id, 0],
+            LogEventType::MessageBroadcast(recievers) => [6, recievers, 0],
+            LogEventType::MessageRecieve(task_id) => [7, task_id, 0],
+            LogEventType::SemaphoreSignal(flags, tasks) => [8, flags, tasks],
+            LogEventType::SemaphoreReset(task_id) => [9, task_id, 0],
+            LogEventType::DeadlineExpired(task_id, deadline) => [10, task_id, deadline],
+            LogEventType::CeilingStack(depth, ceiling) => [11, depth as u32, ceiling],
+            LogEventType::TaskStarvation(task_id) => [12, task_id, 0],
+            #[cfg(any(feature = "events_32", feature = "events_16", feature = "events_64"))]
+            LogEventType::TimerEvent(event_id) => [13, event_id as u32, 0],
+            LogEventType::SemaphoreUnreleasable(tasks_mask) => [14, tasks_mask, 0],
+            LogEventType::BlockedTooLong(task_id) => [15, task_id, 0],
+            LogEventType::SemaphoreBroadcast(tasks) => [16, tasks, 0],
+            LogEventType::SuspendTasks(tasks_mask) => [17, tasks_mask, 0],
+            LogEventType::ResumeTasks(tasks_mask) => [18, tasks_mask, 0],
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -39,10 +75,22 @@ impl LogEvent {
     }
 }
 
+/// What the logger does with a new event when the buffer is full.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OverflowPolicy {
+    /// The oldest event is dropped to make room for the new one.
+    OverwriteOldest,
+    /// The new event is dropped, preserving the earliest events.
+    StopOnFull,
+}
+
 pub struct SystemLogger {
     logs: Logs,
     start: usize,
     end: usize,
+    pub overflow_policy: OverflowPolicy,
+    /// Number of events dropped because the buffer was full.
+    pub dropped: u32,
     pub release_log: bool,
     pub block_tasks_log: bool,
     pub unblock_tasks_log: bool,
@@ -53,6 +101,9 @@ pub struct SystemLogger {
     pub message_recieve_log: bool,
     pub semaphore_signal_log: bool,
     pub semaphore_reset_log: bool,
+    pub task_starvation_log: bool,
+    pub blocked_too_long_log: bool,
+    pub suspend_tasks_log: bool,
     
     #[cfg(any(feature = "events_32", feature = "events_16", feature = "events_64"))]
     pub timer_event_log: bool,
@@ -65,6 +116,8 @@ impl SystemLogger {
             logs: [None; MAX_LOGS],
             start: 0,
             end: 0,
+            overflow_policy: OverflowPolicy::OverwriteOldest,
+            dropped: 0,
             release_log : false,
             block_tasks_log : false,
             unblock_tasks_log : false,
@@ -75,17 +128,28 @@ impl SystemLogger {
             message_recieve_log : false,
             semaphore_signal_log : false,
             semaphore_reset_log : false,
+            task_starvation_log : false,
+            blocked_too_long_log : false,
+            suspend_tasks_log : false,
             
             #[cfg(any(feature = "events_32", feature = "events_16", feature = "events_64"))]
             timer_event_log : false,
         }
     }
     pub fn push(&mut self, event: LogEvent) {
-        self.logs[self.end] = Some(event);
-        self.end = (self.end+1)%MAX_LOGS;
-        if self.start == self.end {
-            self.start = (self.start+1)%MAX_LOGS;
+        let end = (self.end+1)%MAX_LOGS;
+        if end == self.start {
+            self.dropped = self.dropped.saturating_add(1);
+            match self.overflow_policy {
+                OverflowPolicy::StopOnFull => return,
+                OverflowPolicy::OverwriteOldest => {
+                    self.logs[self.start] = None;
+                    self.start = (self.start+1)%MAX_LOGS;
+                }
+            }
         }
+        self.logs[self.end] = Some(event);
+        self.end = end;
     }
     pub fn clear(&mut self) {
         for val in self.logs.iter_mut() {
@@ -95,6 +159,9 @@ impl SystemLogger {
         self.end = 0;
     }
     pub fn pop(&mut self) -> Option<LogEvent> {
+        if self.start == self.end {
+            return None;
+        }
         let pos = self.start;
         let val = self.logs[pos];
         self.logs[pos] = None;
@@ -117,8 +184,15 @@ impl fmt::Debug for LogEventType {
             LogEventType::SemaphoreSignal(tasks_released,tasks_notified) => write!(f, "SemaphoreSignal"),
             LogEventType::SemaphoreReset(task_id) => write!(f, "SemaphoreReset"),
             LogEventType::DeadlineExpired(TaskId, u32) => write!(f, "DeadlineExpired"),
+            LogEventType::CeilingStack(depth, ceiling) => write!(f, "CeilingStack: {} {}", depth, ceiling),
+            LogEventType::TaskStarvation(task_id) => write!(f, "TaskStarvation: {}", task_id),
             #[cfg(any(feature = "events_32", feature = "events_16", feature = "events_64"))]
             LogEventType::TimerEvent(EventId) => write!(f, "TimerEvent"),
+            LogEventType::SemaphoreUnreleasable(tasks_mask) => write!(f, "SemaphoreUnreleasable: {:#b}", tasks_mask),
+            LogEventType::BlockedTooLong(task_id) => write!(f, "BlockedTooLong: {}", task_id),
+            LogEventType::SemaphoreBroadcast(tasks) => write!(f, "SemaphoreBroadcast: {:#b}", tasks),
+            LogEventType::SuspendTasks(tasks_mask) => write!(f, "SuspendTasks: {:#b}", tasks_mask),
+            LogEventType::ResumeTasks(tasks_mask) => write!(f, "ResumeTasks: {:#b}", tasks_mask),
         }
     }
 }
@@ -0,0 +1,60 @@
+//! # Task Local Storage
+//!
+//! A storage slot per task, the slot accessed is the one of the currently running task.
+//!
+//! ## Example
+//! ```rust
+//! static STATUS: TaskLocal<u32, MAX_TASKS> = TaskLocal::new(0);
+//!
+//! STATUS.set(5)?;
+//! let status = STATUS.get();
+//! ```
+use core::cell::RefCell;
+
+use crate::KernelError;
+use crate::kernel::tasks::get_curr_tid;
+use crate::utils::arch::critical_section;
+
+/// Holds a value of type `T` for each of the tasks with a TaskId below `N`, usually `MAX_TASKS`.
+pub struct TaskLocal<T: Copy + Default, const N: usize> {
+    /// The slots, indexed by the TaskId.
+    slots: RefCell<[T; N]>,
+}
+
+impl<T: Copy + Default, const N: usize> TaskLocal<T, N> {
+    /// Initializes the slots of all the tasks with `init`. `Default::default` can't be called in a
+    /// constant, hence statics are initialized through this.
+    pub const fn new(init: T) -> Self {
+        Self {
+            slots: RefCell::new([init; N]),
+        }
+    }
+
+    /// Returns the value in the slot of the currently running task, or `T::default()` if its TaskId
+    /// has no slot.
+    pub fn get(&'static self) -> T {
+        critical_section(|_| self.slots.borrow().get(get_curr_tid() as usize).copied().unwrap_or_default())
+    }
+
+    /// Stores `val` in the slot of the currently running task. Returns `KernelError::NotFound` if its
+    /// TaskId has no slot.
+    pub fn set(&'static self, val: T) -> Result<(), KernelError> {
+        critical_section(|_| {
+            let mut slots = self.slots.borrow_mut();
+            let slot = slots.get_mut(get_curr_tid() as usize).ok_or(KernelError::NotFound)?;
+            *slot = val;
+            Ok(())
+        })
+    }
+}
+
+impl<T: Copy + Default, const N: usize> Default for TaskLocal<T, N> {
+    /// Initializes the slots of all the tasks with `T::default()`.
+    fn default() -> Self {
+        Self {
+            slots: RefCell::new([T::default(); N]),
+        }
+    }
+}
+
+unsafe impl<T: Copy + Default, const N: usize> Sync for TaskLocal<T, N> {}
@@ -1,11 +1,53 @@
-use crate::config::MAX_TASKS;
-use crate::system::scheduler::TaskId;
+use crate::config::{MAX_TASKS, MAX_CONTINUOUS_RUN_TICKS, MAX_BLOCKED_TICKS};
+use crate::system::scheduler::{BooleanVector, TaskId};
 use crate::kernel::logging;
 use crate::system::system_logger::LogEventType;
 
+/// The metrics tracked by the kernel for a single task.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MetricRecord {
+    pub tid: TaskId,
+    /// Maximum cycles observed from being switched in to blocking or exiting.
+    pub max_execution_cycles: u32,
+    /// Number of times the task missed its deadline.
+    pub deadline_misses: u32,
+    /// Number of times the task ran past `MAX_CONTINUOUS_RUN_TICKS` without a context switch.
+    pub starvation_events: u32,
+    /// Number of ticks on which the task was running. For the idle task these are the idle ticks.
+    pub cpu_ticks: u32,
+    /// Longest time the task stayed blocked continuously, its current block included.
+    pub max_blocked_ticks: u32,
+    /// Maximum cycles taken by a context switch to the task, zero without the `latency_monitor` feature.
+    pub max_switch_in_cycles: u32,
+    /// Number of stack words the task has used at most, see `stack_watermark`.
+    pub stack_watermark: u32,
+}
+
 pub struct TaskMonitor {
     active_deadlines: [Option<u32>; MAX_TASKS],
     handler: Option<fn()>,
+    /// Cycle count at which the currently running task was switched in.
+    switched_in_at: u32,
+    /// Cycles consumed by each task in its current execution, accumulated across preemptions.
+    execution_cycles: [u32; MAX_TASKS],
+    /// Maximum cycles observed for each task from being switched in to blocking or exiting.
+    max_execution_cycles: [u32; MAX_TASKS],
+    /// Number of ticks the currently running task has run for since it was switched in.
+    run_ticks: u32,
+    deadline_misses: [u32; MAX_TASKS],
+    starvation_events: [u32; MAX_TASKS],
+    /// Number of ticks on which the idle task was running.
+    idle_ticks: u32,
+    /// Number of ticks on which each task was running.
+    cpu_ticks: [u32; MAX_TASKS],
+    /// Maximum cycles taken by a context switch to each task.
+    max_switch_in_cycles: [u32; MAX_TASKS],
+    /// The time at which each currently blocked task got blocked.
+    blocked_since: [Option<u32>; MAX_TASKS],
+    /// Longest continuous time each task stayed blocked.
+    max_blocked_ticks: [u32; MAX_TASKS],
+    /// Tasks already reported for their current block.
+    reported_blocked: BooleanVector,
 }
 
 impl TaskMonitor {
@@ -13,6 +55,18 @@ impl TaskMonitor {
         Self {
             active_deadlines: [None; MAX_TASKS],
             handler: None,
+            switched_in_at: 0,
+            execution_cycles: [0; MAX_TASKS],
+            max_execution_cycles: [0; MAX_TASKS],
+            run_ticks: 0,
+            deadline_misses: [0; MAX_TASKS],
+            starvation_events: [0; MAX_TASKS],
+            idle_ticks: 0,
+            cpu_ticks: [0; MAX_TASKS],
+            max_switch_in_cycles: [0; MAX_TASKS],
+            blocked_since: [None; MAX_TASKS],
+            max_blocked_ticks: [0; MAX_TASKS],
+            reported_blocked: 0,
         }
     }
     pub fn set_deadline(&mut self, tid: TaskId, abs_deadline: u32) {
@@ -29,6 +83,7 @@ impl TaskMonitor {
             if let Some(deadline) = self.active_deadlines[tid] {
                 if deadline == curr_time {
                     self.active_deadlines[tid] = None;
+                    self.deadline_misses[tid] = self.deadline_misses[tid].saturating_add(1);
                     if self.handler.is_some() {
                         (self.handler.unwrap())();
                     }
@@ -36,4 +91,145 @@ impl TaskMonitor {
             }
         }
     }
-}
\ No newline at end of file
+    /// Accounts the cycles spent by `tid` since it was switched in. If the task is no longer
+    /// ready (it blocked or exited), its execution is complete and the maximum is updated.
+    pub fn switch_out(&mut self, tid: TaskId, is_ready: bool, curr_cycles: u32) {
+        let tid = tid as usize;
+        let elapsed = curr_cycles.wrapping_sub(self.switched_in_at);
+        self.execution_cycles[tid] = self.execution_cycles[tid].saturating_add(elapsed);
+        if !is_ready {
+            if self.execution_cycles[tid] > self.max_execution_cycles[tid] {
+                self.max_execution_cycles[tid] = self.execution_cycles[tid];
+            }
+            self.execution_cycles[tid] = 0;
+        }
+    }
+    pub fn switch_in(&mut self, curr_cycles: u32) {
+        self.switched_in_at = curr_cycles;
+        self.run_ticks = 0;
+    }
+    /// Records the cycles taken by a context switch to `tid`.
+    pub fn record_switch_in(&mut self, tid: TaskId, cycles: u32) {
+        let tid = tid as usize;
+        if cycles > self.max_switch_in_cycles[tid] {
+            self.max_switch_in_cycles[tid] = cycles;
+        }
+    }
+    /// Counts a tick for the running task, returns true when it reaches `MAX_CONTINUOUS_RUN_TICKS`.
+    /// The idle task is never reported, its ticks are counted as idle ticks instead. Before the
+    /// scheduler has `started`, no task runs and the tick isn't counted.
+    pub fn tick_run(&mut self, curr_tid: TaskId, started: bool) -> bool {
+        if !started {
+            return false;
+        }
+        self.cpu_ticks[curr_tid as usize] = self.cpu_ticks[curr_tid as usize].wrapping_add(1);
+        if curr_tid == 0 {
+            self.idle_ticks = self.idle_ticks.wrapping_add(1);
+            return false;
+        }
+        self.run_ticks = self.run_ticks.saturating_add(1);
+        if self.run_ticks == MAX_CONTINUOUS_RUN_TICKS {
+            let tid = curr_tid as usize;
+            self.starvation_events[tid] = self.starvation_events[tid].saturating_add(1);
+            return true;
+        }
+        false
+    }
+    /// Records the time at which the tasks in `tasks_mask` got blocked.
+    pub fn on_block(&mut self, tasks_mask: BooleanVector, curr_time: u32) {
+        for tid in 0..MAX_TASKS {
+            if tasks_mask & (1 << tid) != 0 {
+                self.blocked_since[tid] = Some(curr_time);
+            }
+        }
+    }
+    /// Accounts the time the tasks in `tasks_mask` stayed blocked.
+    pub fn on_unblock(&mut self, tasks_mask: BooleanVector, curr_time: u32) {
+        for tid in 0..MAX_TASKS {
+            if tasks_mask & (1 << tid) != 0 {
+                if let Some(since) = self.blocked_since[tid].take() {
+                    let blocked = curr_time.wrapping_sub(since);
+                    if blocked > self.max_blocked_ticks[tid] {
+                        self.max_blocked_ticks[tid] = blocked;
+                    }
+                }
+            }
+        }
+        self.reported_blocked &= !tasks_mask;
+    }
+    /// Returns the tasks which have just stayed blocked for `MAX_BLOCKED_TICKS`, each block is reported once.
+    pub fn sweep_blocked(&mut self, curr_time: u32) -> BooleanVector {
+        let mut expired = 0;
+        for tid in 0..MAX_TASKS {
+            if let Some(since) = self.blocked_since[tid] {
+                if curr_time.wrapping_sub(since) >= MAX_BLOCKED_TICKS && self.reported_blocked & (1 << tid) == 0 {
+                    expired |= 1 << tid;
+                }
+            }
+        }
+        self.reported_blocked |= expired;
+        expired
+    }
+    /// Returns the longest time the task stayed blocked, its current block included.
+    pub fn get_max_blocked_ticks(&self, tid: TaskId, curr_time: u32) -> u32 {
+        let tid = tid as usize;
+        let current = self.blocked_since[tid].map_or(0, |since| curr_time.wrapping_sub(since));
+        core::cmp::max(self.max_blocked_ticks[tid], current)
+    }
+    pub fn get_idle_ticks(&self) -> u32 {
+        self.idle_ticks
+    }
+    pub fn get_deadline_misses(&self, tid: TaskId) -> u32 {
+        self.deadline_misses[tid as usize]
+    }
+    pub fn get_starvation_events(&self, tid: TaskId) -> u32 {
+        self.starvation_events[tid as usize]
+    }
+    pub fn get_cpu_ticks(&self, tid: TaskId) -> u32 {
+        self.cpu_ticks[tid as usize]
+    }
+    pub fn get_max_switch_in_cycles(&self, tid: TaskId) -> u32 {
+        self.max_switch_in_cycles[tid as usize]
+    }
+    /// Returns all the metrics of the task, `stack_watermark` is kept by the scheduler and passed in.
+    pub fn get_metrics(&self, tid: TaskId, curr_time: u32, stack_watermark: u32) -> MetricRecord {
+        MetricRecord {
+            tid,
+            max_execution_cycles: self.get_max_execution_cycles(tid),
+            deadline_misses: self.get_deadline_misses(tid),
+            starvation_events: self.get_starvation_events(tid),
+            cpu_ticks: self.get_cpu_ticks(tid),
+            max_blocked_ticks: self.get_max_blocked_ticks(tid, curr_time),
+            max_switch_in_cycles: self.get_max_switch_in_cycles(tid),
+            stack_watermark,
+        }
+    }
+    pub fn get_max_execution_cycles(&self, tid: TaskId) -> u32 {
+        self.max_execution_cycles[tid as usize]
+    }
+    pub fn take_idle_ticks(&mut self) -> u32 {
+        core::mem::replace(&mut self.idle_ticks, 0)
+    }
+    pub fn take_deadline_misses(&mut self, tid: TaskId) -> u32 {
+        core::mem::replace(&mut self.deadline_misses[tid as usize], 0)
+    }
+    pub fn take_starvation_events(&mut self, tid: TaskId) -> u32 {
+        core::mem::replace(&mut self.starvation_events[tid as usize], 0)
+    }
+    pub fn take_max_execution_cycles(&mut self, tid: TaskId) -> u32 {
+        core::mem::replace(&mut self.max_execution_cycles[tid as usize], 0)
+    }
+    /// Moves the state of the task `old_tid` to `new_tid`, called when the task changes priority.
+    pub fn move_task(&mut self, old_tid: TaskId, new_tid: TaskId) {
+        let (old, new) = (old_tid as usize, new_tid as usize);
+        self.active_deadlines[new] = self.active_deadlines[old].take();
+        self.execution_cycles[new] = core::mem::replace(&mut self.execution_cycles[old], 0);
+        self.max_execution_cycles[new] = core::mem::replace(&mut self.max_execution_cycles[old], 0);
+        self.deadline_misses[new] = core::mem::replace(&mut self.deadline_misses[old], 0);
+        self.starvation_events[new] = core::mem::replace(&mut self.starvation_events[old], 0);
+        self.blocked_since[new] = self.blocked_since[old].take();
+        self.max_blocked_ticks[new] = core::mem::replace(&mut self.max_blocked_ticks[old], 0);
+        self.cpu_ticks[new] = core::mem::replace(&mut self.cpu_ticks[old], 0);
+        self.max_switch_in_cycles[new] = core::mem::replace(&mut self.max_switch_in_cycles[old], 0);
+    }
+}
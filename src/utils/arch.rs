@@ -3,7 +3,9 @@
 //! Defines functions which are defined majorly in assembly. Thus, might change for one board to another.
 
 // Platform specific Exports
+#[cfg(not(feature = "basepri"))]
 pub use cortex_m::interrupt::free as critical_section;
+pub use cortex_m::interrupt::CriticalSection;
 pub use cortex_m::interrupt::Mutex;
 pub use cortex_m::peripheral::syst::SystClkSource;
 pub use cortex_m::peripheral::Peripherals;
@@ -11,17 +13,61 @@ pub use cortex_m::peripheral::Peripherals;
 use cortex_m_rt::exception;
 use cortex_m::register::control;
 
-use crate::kernel::tasks::{TaskManager,schedule};
-use crate::system::scheduler::TaskControlBlock;
+use crate::kernel::tasks::{TaskManager,schedule,task_yield};
+use crate::system::scheduler::{TaskControlBlock, TaskId};
 
 #[cfg(any(feature = "events_32", feature = "events_16", feature = "events_64"))]
 use crate::kernel::events::sweep_event_table;
 
 #[cfg(feature="task_monitor")]
-use crate::kernel::task_monitor::sweep_deadlines;
+use crate::kernel::task_monitor::{sweep_deadlines, sweep_run_ticks, sweep_blocked, switch_in, switch_out};
 
 #[cfg(feature="timer")]
-use crate::kernel::timer::update_time;
+use crate::kernel::timer::{update_time, sweep_timeouts};
+
+#[cfg(feature = "round_robin")]
+use crate::kernel::tasks::round_robin_tick;
+
+#[cfg(feature="timer")]
+use crate::kernel::tasks::release_periodic;
+
+#[cfg(feature = "latency_monitor")]
+use crate::kernel::latency_monitor::record_context_switch;
+
+#[cfg(all(feature = "latency_monitor", feature = "task_monitor"))]
+use crate::kernel::task_monitor::record_switch_in;
+
+#[cfg(feature = "deterministic")]
+use crate::kernel::decision_trace::decide;
+
+#[cfg(feature = "basepri")]
+use crate::config::KERNEL_BASEPRI;
+
+/// Executes `f` with BASEPRI raised to `KERNEL_BASEPRI`, so that only the interrupts at or below the
+/// kernel's priority are masked and the ones above it keep running. Interrupts above `KERNEL_BASEPRI`
+/// must not call any kernel routine.
+#[cfg(feature = "basepri")]
+pub fn critical_section<F, R>(f: F) -> R
+where
+    F: FnOnce(&CriticalSection) -> R,
+{
+    let basepri = cortex_m::register::basepri::read();
+    unsafe { cortex_m::register::basepri_max::write(KERNEL_BASEPRI) };
+    let res = f(unsafe { &CriticalSection::new() });
+    unsafe { cortex_m::register::basepri::write(basepri) };
+    res
+}
+
+/// Sets PendSV and SysTick to the lowest priority, which is below `KERNEL_BASEPRI`, so that
+/// the context switch and the tick never preempt a kernel critical section.
+#[cfg(feature = "basepri")]
+pub fn set_kernel_handler_priorities() {
+    const SHPR3: *mut u32 = 0xE000_ED20 as *mut u32;
+    unsafe {
+        let shpr3 = core::ptr::read_volatile(SHPR3);
+        core::ptr::write_volatile(SHPR3, shpr3 | 0xFFFF_0000);
+    }
+}
 
 /// Returns the MSB of `val`. It is written using CLZ instruction.
 pub fn get_msb(val: u32) -> Option<usize> {
@@ -42,13 +88,79 @@ pub fn get_msb(val: u32) -> Option<usize> {
     return Some(res);
 }
 
-/// Creates an SVC Interrupt
+/// Kernel services reachable from unprivileged tasks through the SVC instruction.
+/// The discriminant is the SVC number, which indexes `SYSCALL_TABLE`.
+#[derive(Clone, Copy)]
+#[repr(u8)]
+pub enum Syscall {
+    Schedule = 0,
+    Yield = 1,
+}
+
+/// Handlers of the syscalls, indexed by the `Syscall` discriminant. Adding a syscall is adding a variant and its entry here.
+const SYSCALL_TABLE: [fn(); 2] = [
+    schedule,
+    task_yield,
+];
+
+/// Creates an SVC Interrupt for `Syscall::Schedule`
 pub fn svc_call() {
+    unsafe {
+        asm!("svc 0");
+    }
+}
+
+/// Creates an SVC Interrupt for `Syscall::Yield`
+pub fn svc_yield() {
     unsafe {
         asm!("svc 1");
     }
 }
 
+/// Main stack pointer saved by `enter_kernel`, zero if the kernel was started with `start_kernel`.
+static mut KERNEL_SP: usize = 0;
+
+/// Saves the callee-saved registers and the main stack pointer, then pends the first context switch.
+/// It returns only when `return_to_kernel` is executed by PendSV.
+pub fn enter_kernel() {
+    unsafe {
+        asm!(
+            "push	{{r4-r11}}",
+            "mov	r1, sp",
+            "str	r1, [{0}]",
+            "ldr	r1, =0xE000ED04",
+            "mov	r2, #0x10000000",
+            "str	r2, [r1]",
+            "dsb",
+            "isb",
+            "pop	{{r4-r11}}",
+            in(reg) &mut KERNEL_SP,
+            out("r1") _,
+            out("r2") _,
+        );
+        KERNEL_SP = 0;
+    }
+}
+
+/// Returns true if the kernel was entered through `enter_kernel`, and hence PendSV can return to it.
+pub fn can_return_to_kernel() -> bool {
+    unsafe { KERNEL_SP != 0 }
+}
+
+/// Returns from PendSV to `enter_kernel`, by unwinding the main stack to the exception frame
+/// stacked when `enter_kernel` pended the first context switch.
+#[inline(always)]
+pub unsafe fn return_to_kernel() {
+    let frame = (KERNEL_SP & !7) - 32;
+    asm!(
+        "msr	msp, {0}",
+        "ldr	r0, =0xFFFFFFF9",
+        "bx	r0",
+        in(reg) frame,
+        out("r0") _,
+    );
+}
+
 #[inline(always)]
 pub unsafe fn return_to_psp() {
         asm!("
@@ -57,6 +169,7 @@ pub unsafe fn return_to_psp() {
         ");
 }
 
+#[cfg(not(feature = "fpu"))]
 #[inline(always)]
 pub fn save_context(task_stack: &TaskControlBlock) {
     unsafe {
@@ -81,6 +194,7 @@ pub fn save_context(task_stack: &TaskControlBlock) {
     };
 }
 
+#[cfg(not(feature = "fpu"))]
 #[inline(always)]
 pub fn load_context(task_stack: &TaskControlBlock) {
     unsafe {
@@ -104,6 +218,81 @@ pub fn load_context(task_stack: &TaskControlBlock) {
     };
 }
 
+/// Saves the context like the integer-only variant, followed by S0-S31 and FPSCR below it.
+/// The automatic FP stacking is disabled by `disable_fp_stacking`, hence the FPU registers are
+/// saved eagerly for every task.
+#[cfg(feature = "fpu")]
+#[inline(always)]
+pub fn save_context(task_stack: &TaskControlBlock) {
+    unsafe {
+        asm!(
+            ".fpu fpv4-sp-d16",
+            "mrs r0, psp",
+            "subs r0, #16",
+            "stmia r0!,{{r4-r7}}",
+            "mov	r4, r8",
+            "mov	r5, r9",
+            "mov	r6, r10",
+            "mov	r7, r11",
+            "subs	r0, #32",
+            "stmia	r0!,{{r4-r7}}",
+            "subs	r0, #16",
+            "vstmdb	r0!, {{s0-s31}}",
+            "vmrs	r1, fpscr",
+            "str	r1, [r0, #-4]!",
+            "mov	r1, {0}",
+            "str	r0, [r1]",
+            in(reg) task_stack,
+            out("r0") _, 
+            out("r1") _,
+        )
+    };
+}
+
+#[cfg(feature = "fpu")]
+#[inline(always)]
+pub fn load_context(task_stack: &TaskControlBlock) {
+    unsafe {
+        asm!(
+            ".fpu fpv4-sp-d16",
+            "cpsid	i",
+            "mov	r1, {0}",
+            "ldr	r0, [r1]",
+            "ldr	r1, [r0], #4",
+            "vmsr	fpscr, r1",
+            "vldmia	r0!, {{s0-s31}}",
+            "ldmia	r0!,{{r4-r7}}",
+            "mov	r8, r4",
+            "mov	r9, r5",
+            "mov	r10, r6",
+            "mov	r11, r7",
+            "ldmia	r0!,{{r4-r7}}",
+            "msr	psp, r0",
+            in(reg) task_stack,
+            out("r0") _, 
+            out("r1") _,
+        )
+    };
+}
+
+/// Clears ASPEN and LSPEN in FPCCR, so that exception entry never stacks the FPU registers and
+/// the basic 8 word frame is used for every task; the kernel saves them in `save_context` instead.
+///
+/// This applies to every exception, not only to PendSV: an interrupt handler using the FPU would
+/// silently corrupt the FPU registers of the task it interrupted. Hence the `fpu` feature must be
+/// enabled along with `fpu_no_isr_fp`, by which the application states that no handler uses the FPU.
+#[cfg(all(feature = "fpu", feature = "fpu_no_isr_fp"))]
+pub fn disable_fp_stacking() {
+    const FPCCR: *mut u32 = 0xE000_EF34 as *mut u32;
+    unsafe {
+        let fpccr = core::ptr::read_volatile(FPCCR);
+        core::ptr::write_volatile(FPCCR, fpccr & !(0b11 << 30));
+    }
+}
+
+#[cfg(all(feature = "fpu", not(feature = "fpu_no_isr_fp")))]
+compile_error!("the `fpu` feature disables the FP stacking of all exceptions, interrupt handlers must not use the FPU; enable `fpu_no_isr_fp` to confirm they don't");
+
 /// ### SysTick Interrupt handler
 /// Its the Crux of the Kernel’s time management module and Task scheduling.
 /// This interrupt handler updates the time and also dispatches the appropriate event handlers.
@@ -119,43 +308,139 @@ fn SysTick() {
 
     #[cfg(feature="timer")]
     update_time();
+
+    #[cfg(feature="timer")]
+    sweep_timeouts();
+
+    #[cfg(feature="timer")]
+    release_periodic();
     
     #[cfg(feature="task_monitor")]
     sweep_deadlines();
+
+    #[cfg(feature="task_monitor")]
+    sweep_run_ticks();
+
+    #[cfg(feature="task_monitor")]
+    sweep_blocked();
+
+    #[cfg(feature = "round_robin")]
+    round_robin_tick();
     
     // hprintln!("hello");
     schedule();
 }
 /// ### SVC Interrupt handler,
-/// calls `tasks::schedule()`
+/// Dispatches the syscall through `SYSCALL_TABLE`. SVCs are only raised by tasks, which run on the PSP,
+/// so the stacked PC is read from the process stack; the SVC number is the low byte of the instruction before it.
 #[exception]
 fn SVCall() {
-    schedule();
+    let stack_frame = cortex_m::register::psp::read() as *const u32;
+    let syscall = unsafe {
+        let pc = *stack_frame.offset(6) as *const u8;
+        *pc.offset(-2) as usize
+    };
+    if let Some(handler) = SYSCALL_TABLE.get(syscall) {
+        handler();
+    }
 }
 /// ### PendSV Interrupt handler,
 /// PendSV interrupt handler does the actual context switch in the Kernel.
 #[exception]
 fn PendSV() {
-    critical_section(|cs_token| {
+    #[cfg(feature = "latency_monitor")]
+    let switch_start = get_cycle_count();
+    let is_shutdown = critical_section(|cs_token| {
         let handler = &mut TaskManager.borrow(cs_token).borrow_mut();
         let curr_tid: usize = handler.curr_tid;
+        if handler.started && handler.shutdown.is_some() {
+            let curr_task = handler.task_control_blocks[curr_tid].as_ref().unwrap();
+            curr_task.save_context();
+            #[cfg(feature="task_monitor")]
+            switch_out(curr_tid as TaskId, handler.is_ready(curr_tid));
+            handler.started = false;
+            return true;
+        }
         let next_tid: usize = handler.get_next_tid() as usize;
+        #[cfg(feature = "deterministic")]
+        let next_tid = decide(next_tid as TaskId, handler.ready_tasks()) as usize;
         if curr_tid != next_tid || (!handler.started) {
             if handler.started {
+                handler.last_switch_reason = Some(handler.switch_reason(curr_tid));
                 let curr_task = handler.task_control_blocks[curr_tid].as_ref().unwrap();
                 curr_task.save_context();
+                #[cfg(feature="task_monitor")]
+                switch_out(curr_tid as TaskId, handler.is_ready(curr_tid));
             } else {
                 handler.started = true;
             }
+            #[cfg(feature="task_monitor")]
+            switch_in();
             let next_task = handler.task_control_blocks[next_tid].as_ref().unwrap();
+            #[cfg(feature = "latency_monitor")] {
+                let switch_cycles = get_cycle_count().wrapping_sub(switch_start);
+                record_context_switch(switch_cycles);
+                #[cfg(feature="task_monitor")]
+                record_switch_in(next_tid as TaskId, switch_cycles);
+            }
             next_task.load_context();
+            set_thread_privilege(next_task.is_privileged());
     
             handler.curr_tid = next_tid;
+            handler.context_switches = handler.context_switches.wrapping_add(1);
         }
+        handler.switch_hint = None;
+        false
     });
+    if is_shutdown {
+        unsafe { return_to_kernel() };
+        return;
+    }
+    // `load_context` disables interrupts through PRIMASK, which the BASEPRI critical section doesn't restore.
+    #[cfg(feature = "basepri")]
+    unsafe { cortex_m::interrupt::enable() };
     unsafe {return_to_psp()}
 }
 
+/// Returns the process stack pointer, which is the stack pointer of the running task.
+pub fn get_psp() -> usize {
+    cortex_m::register::psp::read() as usize
+}
+
+/// Returns the current value of the DWT cycle counter.
+pub fn get_cycle_count() -> u32 {
+    cortex_m::peripheral::DWT::get_cycle_count()
+}
+
+/// Requests a system reset, it never returns.
+pub fn system_reset() -> ! {
+    cortex_m::peripheral::SCB::sys_reset()
+}
+
+/// An interrupt identified by its number in the NVIC.
+struct IrqNumber(u8);
+
+unsafe impl cortex_m::interrupt::Nr for IrqNumber {
+    fn nr(&self) -> u8 {
+        self.0
+    }
+}
+
+/// Disables the interrupt `irq` in the NVIC.
+pub fn mask_irq(irq: u8) {
+    cortex_m::peripheral::NVIC::mask(IrqNumber(irq));
+}
+
+/// Enables the interrupt `irq` in the NVIC.
+pub fn unmask_irq(irq: u8) {
+    unsafe { cortex_m::peripheral::NVIC::unmask(IrqNumber(irq)) };
+}
+
+/// Returns true if PendSV is already pending.
+pub fn is_pendsv_pending() -> bool {
+    cortex_m::peripheral::SCB::is_pendsv_pending()
+}
+
 pub fn set_pendsv() {
     cortex_m::peripheral::SCB::set_pendsv();
 }
@@ -164,7 +449,28 @@ pub fn wait_for_interrupt() {
     cortex_m::asm::wfi();
 }
 
-/// Returns true if Currently the Kernel is operating in Privileged mode.
+/// Returns true if Currently the Kernel is operating in Privileged mode. Exception handlers are
+/// always privileged, whatever the privilege of the interrupted task.
 pub fn is_privileged() -> bool {
-    return control::read().npriv() == control::Npriv::Privileged
+    let ipsr: u32;
+    unsafe {
+        asm!("mrs {0}, IPSR", out(reg) ipsr);
+    }
+    return ipsr != 0 || control::read().npriv() == control::Npriv::Privileged
+}
+
+/// Sets the privilege of thread mode, which takes effect on the return from the current exception.
+pub fn set_thread_privilege(privileged: bool) {
+    let mut control: u32;
+    unsafe {
+        asm!("mrs {0}, CONTROL", out(reg) control);
+    }
+    control = if privileged { control & !1 } else { control | 1 };
+    unsafe {
+        asm!(
+            "msr CONTROL, {0}",
+            "isb",
+            in(reg) control,
+        );
+    }
 }
\ No newline at end of file
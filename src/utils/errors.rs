@@ -10,6 +10,13 @@ pub enum KernelError {
     AccessDenied,
     Empty,
     Exists,
+    LockOrderViolation,
+    HeldResource,
+    Timeout,
+    StackOverlap,
+    CycleDetected,
+    InvalidTask,
+    NotOwner,
 }
 
 impl fmt::Debug for KernelError {
@@ -21,6 +28,13 @@ impl fmt::Debug for KernelError {
             KernelError::AccessDenied => write!(f, "AccessDenied"),
             KernelError::Empty => write!(f, "Empty"),
             KernelError::Exists => write!(f, "Exists"),
+            KernelError::LockOrderViolation => write!(f, "LockOrderViolation"),
+            KernelError::HeldResource => write!(f, "HeldResource"),
+            KernelError::Timeout => write!(f, "Timeout"),
+            KernelError::StackOverlap => write!(f, "StackOverlap"),
+            KernelError::CycleDetected => write!(f, "CycleDetected"),
+            KernelError::InvalidTask => write!(f, "InvalidTask"),
+            KernelError::NotOwner => write!(f, "NotOwner"),
         }
     }
 }